@@ -1,44 +0,0 @@
-sed -r -e 's/"head_templates": \[[^]]*\],\s//g'     \
--e 's/"raw_glosses": \["[^"]*"\],\s//g'     \
--e 's/, "id": "[^"]*"//g'                   \
--e 's/, "args": \{"[^}]*"\}//g'             \
-\
--e 's/\{"form": "[^"]*", ("source": "[^"]*", )?("roman": "[^"]*", )?"tags": \[[^]]*\](, "source": "[^"]*")?\},\s//g'   \
--e 's/, \{"form": "[^"]*", ("source": "[^"]*", )?("roman": "[^"]*", )?"tags": \[[^]]*\](, "source": "[^"]*")?\}//g'    \
--e 's/\{"form": "[^"]*", ("source": "[^"]*", )?("roman": "[^"]*", )?"tags": \[[^]]*\](, "source": "[^"]*")?\}//g'      \
-\
--e 's/\{"audio": "[^"]*", ("text": "[^"]*", )?("audio-ipa": "[^"]*", )?("tags": \[[^]]*\], )?"ogg_url": "[^"]*", "mp3_url": "[^"]*"\},\s//g' \
--e 's/, \{"audio": "[^"]*", ("text": "[^"]*", )?("audio-ipa": "[^"]*", )?("tags": \[[^]]*\], )?"ogg_url": "[^"]*", "mp3_url": "[^"]*"\}//g'  \
--e 's/\{"audio": "[^"]*", ("text": "[^"]*", )?("audio-ipa": "[^"]*", )?("tags": \[[^]]*\], )?"ogg_url": "[^"]*", "mp3_url": "[^"]*"\}//g'    \
-\
--e 's/\{"ipa": "[^"]*"\},\s//g'             \
--e 's/, \{"ipa": "[^"]*"\}//g'              \
--e 's/\{"ipa": "[^"]*"\}//g'                \
-\
--e 's/\{"ipa": "[^"]*", "tags": \[[^]]*\]\},\s//g'  \
--e 's/, \{"ipa": "[^"]*", "tags": \[[^]]*\]\}//g'   \
--e 's/\{"ipa": "[^"]*", "tags": \[[^]]*\]\}//g'     \
-\
--e 's/\{"ipa": "[^"]*", "note": "[^"]*"\},\s//g'    \
--e 's/, \{"ipa": "[^"]*", "note": "[^"]*"\}//g'     \
--e 's/\{"ipa": "[^"]*", "note": "[^"]*"\}//g'       \
-\
--e 's/\{"rhymes": "[^"]*"\},\s//g'          \
--e 's/, \{"rhymes": "[^"]*"\}//g'           \
--e 's/\{"rhymes": "[^"]*"\}//g'             \
-\
--e 's/\{"homophone": "[^"]*"\},\s//g'       \
--e 's/, \{"homophone": "[^"]*"\}//g'        \
--e 's/\{"homophone": "[^"]*"\}//g'          \
--e 's/, \{"enpr": "[^"]*"\}//g'             \
--e 's/, "parents": \[[^]]*\]//g'            \
--e 's/"roman": "[^"]*", //g'                \
--e 's/, ("kind"|"source"|"_dis1?"|"orig"|"langcode"): "[^"]*"//g'       \
-\
--e 's/\{"lang": "[^"]*", "code": "[^"]*", "sense": "[^"]*"(, "tags": \[[^]]*\])?(, "note": "[^"]*")?(, "word": "[^"]*")?(, "_dis1": [^}]*)?\},\s//g' \
--e 's/, \{"lang": "[^"]*", "code": "[^"]*", "sense": "[^"]*"(, "tags": \[[^]]*\])?(, "note": "[^"]*")?(, "word": "[^"]*")?(, "_dis1": [^}]*)?\}//g' \
--e 's/\{"lang": "[^"]*", "code": "[^"]*", "sense": "[^"]*"(, "tags": \[[^]]*\])?(, "note": "[^"]*")?(, "word": "[^"]*")?(, "_dis1": [^}]*)?\}//g' \
-\
--e 's/"[^"]+": \[\],\s//g'                  \
--e 's/, "[^"]+": \[\]//g'                   \
--e 's/"[^"]+": \[\]//g'       $1 > $2
\ No newline at end of file
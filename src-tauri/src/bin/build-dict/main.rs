@@ -0,0 +1,90 @@
+//! Prepares the offline dictionaries published for `download_dict` from the Wiktextract dumps of kaikki.org.
+//!
+//! `cargo run --bin build-dict -- <kaikki dump.json> <language code> [output directory]`
+//!
+//! Every entry of the dump is pruned to the fields listed in `prune::ENTRY_FIELDS` and the result is
+//! packed as `incorrect_{code}.tar.xz`, holding `incorrect_{code}.json` with one entry per line.
+//! The same dump always gives the same archive, whose SHA-256 digest is printed for the catalog.
+
+mod prune;
+
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    process,
+};
+use xz::write::XzEncoder;
+
+const XZ_LEVEL: u32 = 9;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    if !(2..=3).contains(&args.len()) {
+        eprintln!("usage: build-dict <kaikki dump.json> <language code> [output directory]");
+        process::exit(2);
+    }
+    let out_dir = args.get(2).map_or(".", |dir| dir.as_str());
+    if let Err(e) = build(&args[0], &args[1], Path::new(out_dir)) {
+        eprintln!("build-dict: {e}");
+        process::exit(1);
+    }
+}
+
+fn build(dump: &str, code: &str, out_dir: &Path) -> Result<(), Box<dyn Error>> {
+    // same rule as the app applies to dictionary codes.
+    let valid_code = code.len() <= 16
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if code.is_empty() || !valid_code {
+        Err(format!("invalid language code {code}"))?
+    }
+    fs::create_dir_all(out_dir)?;
+
+    let json_name = format!("incorrect_{code}.json");
+    let json_path = out_dir.join(&json_name);
+    let dump = BufReader::new(File::open(dump)?);
+    let (kept, skipped) = prune_dump(dump, BufWriter::new(File::create(&json_path)?))?;
+
+    let archive_path = out_dir.join(format!("incorrect_{code}.tar.xz"));
+    let archive = BufWriter::new(File::create(&archive_path)?);
+    let mut tar = tar::Builder::new(XzEncoder::new(archive, XZ_LEVEL));
+    // a fixed header instead of the metadata of the file keeps the archive reproducible.
+    let mut header = tar::Header::new_gnu();
+    header.set_size(fs::metadata(&json_path)?.len());
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    tar.append_data(&mut header, &json_name, File::open(&json_path)?)?;
+    tar.into_inner()?.finish()?.flush()?;
+    fs::remove_file(&json_path)?;
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(&archive_path)?, &mut hasher)?;
+    println!("{kept} entries kept, {skipped} lines skipped");
+    println!("{:x}  {}", hasher.finalize(), archive_path.display());
+    Ok(())
+}
+
+/// Writes the pruned entries of `dump` to `out`, one per line. Returns how many lines were kept and skipped.
+fn prune_dump<R: BufRead, W: Write>(dump: R, mut out: W) -> Result<(usize, usize), Box<dyn Error>> {
+    let (mut kept, mut skipped) = (0, 0);
+    for line in dump.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match prune::prune_line(&line) {
+            Some(entry) => {
+                writeln!(out, "{entry}")?;
+                kept += 1;
+            }
+            None => skipped += 1,
+        }
+    }
+    out.flush()?;
+    Ok((kept, skipped))
+}
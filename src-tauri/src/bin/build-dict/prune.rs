@@ -0,0 +1,140 @@
+use serde_json::{Map, Value};
+
+/// A field kept in the published dictionaries. Objects, and arrays of objects, under a field with
+/// `fields` keep only those, a field without `fields` is kept as it is.
+pub struct Field {
+    name: &'static str,
+    fields: &'static [Field],
+}
+
+const fn keep(name: &'static str) -> Field {
+    Field { name, fields: &[] }
+}
+
+const fn nested(name: &'static str, fields: &'static [Field]) -> Field {
+    Field { name, fields }
+}
+
+static LINK_FIELDS: &[Field] = &[keep("word")];
+
+static SENSE_FIELDS: &[Field] = &[
+    keep("glosses"),
+    keep("tags"),
+    nested("categories", &[keep("name")]),
+    nested("form_of", LINK_FIELDS),
+    nested("alt_of", LINK_FIELDS),
+    nested(
+        "examples",
+        &[keep("text"), keep("english"), keep("type"), keep("ref")],
+    ),
+];
+
+/// Everything of a Wiktextract entry the app shows or indexes. Forms, sounds, translations,
+/// head templates and the like are left out.
+pub static ENTRY_FIELDS: &[Field] = &[
+    keep("word"),
+    keep("lang"),
+    keep("lang_code"),
+    keep("pos"),
+    keep("etymology_text"),
+    nested("etymology_templates", &[keep("name"), keep("expansion")]),
+    nested("senses", SENSE_FIELDS),
+    nested("related", LINK_FIELDS),
+];
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        Value::String(s) => s.is_empty(),
+        Value::Null => true,
+        _ => false,
+    }
+}
+
+/// Drops from `value` whatever `fields` does not list, then every array, object or string left empty.
+pub fn prune(value: Value, fields: &[Field]) -> Option<Value> {
+    let pruned = match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .filter_map(|(name, value)| {
+                    let field = fields.iter().find(|field| field.name == name)?;
+                    let value = match field.fields {
+                        [] => value,
+                        fields => prune(value, fields)?,
+                    };
+                    Some((name, value))
+                })
+                .filter(|(_, value)| !is_empty(value))
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .filter_map(|item| prune(item, fields))
+                .collect(),
+        ),
+        value => value,
+    };
+    (!is_empty(&pruned)).then_some(pruned)
+}
+
+/// The pruned JSON of a line of a Wiktextract dump, `None` for lines that are not an entry.
+pub fn prune_line(line: &str) -> Option<String> {
+    let entry = prune(serde_json::from_str(line).ok()?, ENTRY_FIELDS)?;
+    entry.get("word")?.as_str()?;
+    serde_json::to_string(&entry).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prune_line;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn only_allowed_fields_are_kept() {
+        let line = json!({
+            "word": "Haus", "lang": "German", "lang_code": "de", "pos": "noun",
+            "head_templates": [{"name": "de-noun", "args": {"1": "n"}}],
+            "forms": [{"form": "Häuser", "tags": ["plural"]}],
+            "sounds": [{"ipa": "/haʊ̯s/"}, {"audio": "De-Haus.ogg", "ogg_url": "https://x"}],
+            "translations": [{"lang": "English", "code": "en", "sense": "house", "word": "house"}],
+            "etymology_templates": [{"name": "inh", "args": {"1": "de"}, "expansion": "Middle High German hūs"}],
+            "senses": [
+                {
+                    "glosses": ["house"], "raw_glosses": ["house"], "id": "de-Haus-noun-1",
+                    "categories": [{"name": "German nouns", "kind": "other", "parents": [], "source": "w"}],
+                    "examples": [{"text": "Das Haus ist groß.", "roman": "x", "english": "The house is big."}],
+                    "synonyms": []
+                },
+                {"id": "de-Haus-noun-2", "raw_glosses": ["(colloquial) person"]}
+            ]
+        })
+        .to_string();
+
+        let pruned = serde_json::from_str::<Value>(&prune_line(&line).unwrap()).unwrap();
+        assert_eq!(
+            pruned,
+            json!({
+                "word": "Haus", "lang": "German", "lang_code": "de", "pos": "noun",
+                "etymology_templates": [{"name": "inh", "expansion": "Middle High German hūs"}],
+                "senses": [{
+                    "glosses": ["house"],
+                    "categories": [{"name": "German nouns"}],
+                    "examples": [{"text": "Das Haus ist groß.", "english": "The house is big."}]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn lines_without_a_headword_are_skipped() {
+        assert_eq!(prune_line(r#"{"pos": "noun", "senses": []}"#), None);
+        assert_eq!(prune_line(r#"{"word": 1}"#), None);
+        assert_eq!(prune_line("not json"), None);
+        assert_eq!(
+            prune_line(r#"{"word": "say \"hi\"", "sounds": []}"#).unwrap(),
+            r#"{"word":"say \"hi\""}"#
+        );
+    }
+}
@@ -1,125 +1,67 @@
 use futures_util::StreamExt;
-use ijson::IObject;
+use ijson::IValue;
+use reqwest::{header::RANGE, StatusCode};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::error::Error;
 use std::{
-    collections::HashMap,
     fs::{self, File},
-    io::{self, Read, Write},
-    path::PathBuf,
-    sync::{mpsc, Arc, Mutex},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
     thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc;
+
+use crate::http::{client, download_client};
+use crate::offline_dict::{
+    build_from_archive, build_from_dictd, build_from_jsonl, build_from_legacy_json,
+    build_from_stardict, find_catalog_dict, is_valid_abbr, verify_archive, BuildPhase,
+    BuildProgress, CatalogDict, ChunkReader, DictRegistry, ProgressReader, CANCELED,
+    DICT_META_FILENAME,
 };
-use tauri::regex::Regex;
-use xz::read::XzDecoder;
-
-use crate::online_translate::CLIENT;
 
 pub static JSON_DIR: &str = "json_dictionaries";
 pub static SETTINGS_FILENAME: &str = "settings";
-
-pub struct OfflineDict<'a> {
-    url: &'a str,
-    length_mb: u64,
-    name: &'a str,
-}
+/// How many times a dropped connection is resumed within one `download_dict` call.
+const MAX_DOWNLOAD_ATTEMPTS: u32 = 3;
+/// Downloaded chunks waiting for the converter, this bounds the memory a download takes.
+const PIPELINE_CHUNKS: usize = 64;
+const IMPORT_BUFFER_SIZE: usize = 1024 * 1024;
+static CONVERTER_STOPPED: &str = "the dictionary converter stopped";
+/// Version recorded for dictionaries imported from a file, they have no catalog entry to update from.
+static IMPORTED_VERSION: &str = "local";
+/// Version recorded for dictionaries converted from the single json file of older versions. It
+/// matches no catalog version, so an update is offered for them.
+static LEGACY_VERSION: &str = "legacy";
 
 #[derive(Serialize, Clone)]
 struct DictDowlonadStatus<'a> {
     name: &'a str,
+    phase: BuildPhase,
     percentage: i8,
 }
 
 lazy_static! {
-    static ref JSON_REGEX: Regex = Regex::new(r#"(?m).*"word": "([^"]+)", "lang".*"#).unwrap();
-    static ref RESOURCE_PATH_BUF: PathBuf = tauri::api::path::resource_dir(tauri::generate_context!().package_info(), &tauri::Env::default()).unwrap();
-    static ref IDENTIFIER: String = format!("{}", tauri::generate_context!().config().tauri.bundle.identifier);
+    static ref RESOURCE_PATH_BUF: PathBuf = tauri::api::path::resource_dir(
+        tauri::generate_context!().package_info(),
+        &tauri::Env::default()
+    )
+    .unwrap();
+    static ref IDENTIFIER: String = format!(
+        "{}",
+        tauri::generate_context!().config().tauri.bundle.identifier
+    );
     static ref CACHE_PATH_BUF: PathBuf = tauri::api::path::cache_dir().unwrap();
-    pub static ref CACHE_PATH_WITH_IDENTIFIER: String = format!("{}/{}", CACHE_PATH_BUF.to_str().unwrap(), IDENTIFIER.to_string());
-    pub static ref EN_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/en"))).or(Err(format!("error occurred for en dict")));
-    pub static ref FR_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/fr"))).or(Err(format!("error occurred for fr dict")));
-    pub static ref DE_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/de"))).or(Err(format!("error occurred for de dict")));
-    pub static ref ES_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/es"))).or(Err(format!("error occurred for es dict")));
-    pub static ref IT_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/it"))).or(Err(format!("error occurred for it dict")));
-    pub static ref FA_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/fa"))).or(Err(format!("error occurred for fa dict")));
-    pub static ref PT_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/pt"))).or(Err(format!("error occurred for pt dict")));
-    pub static ref ZH_CN_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/zh-CN"))).or(Err(format!("error occurred for zh-CN dict")));
-    pub static ref AR_DICT: Result<IObject, String> = read_json_file(&find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/ar"))).or(Err(format!("error occurred for ar dict")));
-    pub static ref OFFLINE_DICTS: HashMap<&'static str, OfflineDict<'static>> = HashMap::from([
-        (
-            "en",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_en.tar.xz",
-                length_mb: 90,
-                name: "English"
-            }
-        ),
-        (
-            "fr",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_fr.tar.xz",
-                length_mb: 25,
-                name: "French"
-            }
-        ),
-        (
-            "de",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_de.tar.xz",
-                length_mb: 41,
-                name: "German"
-            }
-        ),
-        (
-            "es",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_es.tar.xz",
-                length_mb: 39,
-                name: "Spanish"
-            }
-        ),
-        (
-            "it",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_it.tar.xz",
-                length_mb: 32,
-                name: "Italian"
-            }
-        ),
-        (
-            "fa",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_fa.tar.xz",
-                length_mb: 3,
-                name: "Persian"
-            }
-        ),
-        (
-            "pt",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_pt.tar.xz",
-                length_mb: 20,
-                name: "Portuguese"
-            }
-        ),
-        (
-            "zh-CN",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect-zh-CN.tar.xz",
-                length_mb: 47,
-                name: "Chinese"
-            }
-        ),
-        (
-            "ar",
-            OfflineDict {
-                url: "https://github.com/Arian94/tiny-beast-dictionary/releases/download/v.12.55/incorrect_ar.tar.xz",
-                length_mb: 20,
-                name: "Arabic"
-            }
-        )
-    ]);
+    pub static ref CACHE_PATH_WITH_IDENTIFIER: String = format!(
+        "{}/{}",
+        CACHE_PATH_BUF.to_str().unwrap(),
+        IDENTIFIER.to_string()
+    );
+    pub static ref LOADED_DICTS: DictRegistry =
+        DictRegistry::new(find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, JSON_DIR));
+    static ref SETTINGS: RwLock<Option<Arc<IValue>>> = RwLock::new(None);
 }
 
 pub fn find_absolute_path(base_path: &str, path: &str) -> String {
@@ -127,6 +69,11 @@ pub fn find_absolute_path(base_path: &str, path: &str) -> String {
     absolute_path
 }
 
+/// Directory holding the on-disk index of an offline dictionary.
+pub fn dict_dir(abbr: &str) -> String {
+    find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, &format!("{JSON_DIR}/{abbr}"))
+}
+
 pub fn read_json_file<T>(path: &str) -> Result<T, Box<dyn Error + Send + Sync>>
 where
     T: DeserializeOwned + std::fmt::Debug,
@@ -139,119 +86,187 @@ where
     Ok(json_file)
 }
 
+/// `settings.json`, read on first use and cached until `reload_settings`.
+pub fn read_settings() -> Option<Arc<IValue>> {
+    if let Some(settings) = SETTINGS.read().unwrap().as_ref() {
+        return Some(Arc::clone(settings));
+    }
+    let settings = Arc::new(
+        read_json_file::<IValue>(&find_absolute_path(
+            &CACHE_PATH_WITH_IDENTIFIER,
+            SETTINGS_FILENAME,
+        ))
+        .ok()?,
+    );
+    *SETTINGS.write().unwrap() = Some(Arc::clone(&settings));
+    Some(settings)
+}
+
+/// Drops the cached settings once `settings.json` has been written, the next read gets the new ones.
+pub fn reload_settings() {
+    *SETTINGS.write().unwrap() = None;
+}
+
+/// A non-empty string setting from `settings.json`, trimmed.
+pub fn setting_str(key: &str) -> Option<String> {
+    let settings = read_settings()?;
+    let value = settings.get(key)?.as_string()?.as_str().trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// A setting from `settings.json` holding a list of strings, other items are skipped.
+pub fn setting_str_list(key: &str) -> Option<Vec<String>> {
+    let settings = read_settings()?;
+    let items = settings.get(key)?.as_array()?;
+    Some(
+        items
+            .iter()
+            .filter_map(|item| Some(item.as_string()?.as_str().to_owned()))
+            .collect(),
+    )
+}
+
+/// A non-negative integer setting from `settings.json`.
+pub fn setting_u64(key: &str) -> Option<u64> {
+    read_settings()?.get(key)?.to_u64()
+}
+
+/// A non-negative integer under `name` in an object setting from `settings.json`.
+pub fn setting_u64_entry(key: &str, name: &str) -> Option<u64> {
+    read_settings()?.get(key)?.as_object()?.get(name)?.to_u64()
+}
+
 pub fn delete_json_file(path: &str) -> io::Result<()> {
     let name = format!("{path}.json");
     fs::remove_file(name)?;
     Ok(())
 }
 
-pub fn open_write_json_payload<T>(filename: &str, payload: &str) -> Result<(), String>
-where
-    T: Serialize + DeserializeOwned + std::fmt::Debug,
-{
-    let name = format!("{filename}.json");
-    let config_value = serde_json::from_str::<T>(payload).unwrap();
-
-    match File::options().write(true).truncate(true).open(&name) {
-        Ok(file) => {
-            match serde_json::to_writer(file, &config_value) {
-                Ok(it) => return Ok(it),
-                Err(err) => return Err(err.to_string()),
-            };
+/// Moves a freshly built dictionary into place. The installed one is unloaded first, as mapped files
+/// can't be moved on Windows, and set aside until the new one is in, so that it is never lost halfway.
+fn install_dict_dir(abbr: &str, built_dir: &str) -> io::Result<()> {
+    let dir = dict_dir(abbr);
+    let old_dir = format!("{dir}.old");
+    if fs::metadata(&old_dir).is_ok() {
+        fs::remove_dir_all(&old_dir)?;
+    }
+    LOADED_DICTS.unload(abbr);
+    let replacing = fs::metadata(&dir).is_ok();
+    if replacing {
+        fs::rename(&dir, &old_dir)?;
+    }
+    if let Err(e) = fs::rename(built_dir, &dir) {
+        if replacing {
+            fs::rename(&old_dir, &dir)?;
         }
-        Err(err) => {
-            eprintln!("open_write_json_payload: {err} ---> {name} is created");
-            create_write_json_file(&filename, config_value)
+        return Err(e);
+    }
+    if replacing {
+        fs::remove_dir_all(old_dir)?;
+    }
+    Ok(())
+}
+
+/// Puts back the dictionaries `install_dict_dir` set aside when the app stopped before the new ones were in.
+pub fn restore_set_aside_dicts() {
+    let set_aside = match fs::read_dir(find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, JSON_DIR)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|name| name.strip_suffix(".old").map(str::to_owned))
+            .filter(|abbr| is_valid_abbr(abbr))
+            .collect::<Vec<String>>(),
+        Err(_) => return,
+    };
+    for abbr in &set_aside {
+        let dir = dict_dir(abbr);
+        let restored = match fs::metadata(&dir) {
+            Ok(_) => fs::remove_dir_all(format!("{dir}.old")),
+            Err(_) => fs::rename(format!("{dir}.old"), &dir),
+        };
+        if let Err(e) = restored {
+            eprintln!("error in restoring {abbr} dictionary: {e}");
         }
     }
 }
 
-fn rectify_incorrect_string(
-    incorrect_string: &String,
-    abbr: &str,
-    file_path: &str,
-) -> Result<(), Box<dyn Error>> {
-    let file_path = format!("{file_path}.json");
-    let name = OFFLINE_DICTS
-        .get(&abbr)
-        .unwrap_or(&OfflineDict {
-            url: "",
-            length_mb: 0,
-            name: "unknown",
-        })
-        .name;
-
-    let dict_file = File::create(&file_path)?;
-    let dict_file_arc = Arc::new(Mutex::new(dict_file));
-    let counter_arc = Arc::new(Mutex::new(0));
-
-    let mut vector_of_lines = incorrect_string.split("\n").collect::<Vec<&str>>();
-    vector_of_lines.pop();
-
-    let l = vector_of_lines.len();
-    let number_of_threads = 4;
-    let slot = l / number_of_threads;
-
-    let lines_1 = vector_of_lines.get(0..slot).unwrap().join("\n"); //* join by \n so that the regex will work correclty. */
-    let lines_2 = vector_of_lines.get(slot..2 * slot).unwrap().join("\n");
-    let lines_3 = vector_of_lines.get(2 * slot..3 * slot).unwrap().join("\n");
-    let lines_4 = vector_of_lines.get(3 * slot..l).unwrap().join("\n");
-
-    let all_four = vec![lines_1, lines_2, lines_3, lines_4];
-
-    let mut threads = Vec::new();
-    for lines in all_four {
-        let abbr = abbr.to_owned();
-        let dict_arc_clone = dict_file_arc.clone();
-        let counter_arc_clone = counter_arc.clone();
-
-        let thread = thread::spawn(move || -> Result<(), &str> {
-            let mut correct_seg_string = String::new();
-            let mut matches = JSON_REGEX.captures_iter(&lines);
-            while let Some(c) = matches.next() {
-                let removed_val = &c[0];
-                let word = &c[1];
-                let to_be_removed = format!(
-                    "\"word\": \"{word}\", \"lang\": \"{name}\", \"lang_code\": \"{abbr}\","
-                );
-                let removed_val = removed_val.replace(&to_be_removed, "");
-                let res = format!("\"{word}\":{removed_val},");
-                correct_seg_string.push_str(&res);
-            }
+pub fn delete_dict_dir(abbr: &str) -> io::Result<()> {
+    fs::remove_dir_all(dict_dir(abbr))?;
+    Ok(())
+}
 
-            if let Ok(file) = dict_arc_clone.lock().as_mut() {
-                if let Ok(mut counter) = counter_arc_clone.lock() {
-                    *counter += 1;
-                    if *counter == 1 {
-                        correct_seg_string.insert(0, '{');
-                    }
-                    if *counter == number_of_threads {
-                        correct_seg_string.pop(); // pop last comma which is extra.
-                        correct_seg_string.push('}');
-                    }
-                } else {
-                    return Err("error in locking counter");
-                }
-                file.write_all(correct_seg_string.as_bytes())
-                    .or(Err("error in writing chunk"))?;
-                Ok(())
-            } else {
-                Err("error in locking file")
-            }
-        });
-        threads.push(thread);
+/// Dictionaries downloaded before the on-disk index were a single `{abbr}.json`. Each is converted
+/// into an index in place, and its json is only deleted once the index is installed, so a failed
+/// conversion is tried again on the next start.
+pub fn migrate_legacy_dicts() {
+    let legacy = match fs::read_dir(find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, JSON_DIR)) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|name| name.strip_suffix(".json").map(str::to_owned))
+            .filter(|abbr| is_valid_abbr(abbr) && fs::metadata(dict_dir(abbr)).is_err())
+            .collect::<Vec<String>>(),
+        Err(_) => return,
+    };
+    for abbr in &legacy {
+        if let Err(e) = migrate_legacy_dict(abbr) {
+            eprintln!("error in converting legacy {abbr} dictionary: {e}");
+        }
     }
+}
 
-    for thread in threads {
-        thread.join().unwrap().unwrap();
+fn migrate_legacy_dict(abbr: &str) -> Result<(), String> {
+    let legacy_path = format!("{}.json", dict_dir(abbr));
+    let file = File::open(&legacy_path).or(Err("error in reading dictionary file"))?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let partial_dir = format!("{}.partial", dict_dir(abbr));
+    let reader = BufReader::with_capacity(IMPORT_BUFFER_SIZE, file);
+    if let Err(e) = build_from_legacy_json(reader, &partial_dir, &Default::default()) {
+        if fs::metadata(&partial_dir).is_ok() {
+            fs::remove_dir_all(&partial_dir).or(Err("error in deleting partial dictionary"))?;
+        }
+        return Err(e.to_string());
     }
 
+    let meta = match find_catalog_dict(abbr) {
+        Some(dict) => CatalogDict {
+            version: LEGACY_VERSION.to_owned(),
+            ..dict
+        },
+        None => CatalogDict {
+            code: abbr.to_owned(),
+            name: abbr.to_owned(),
+            version: LEGACY_VERSION.to_owned(),
+            url: legacy_path.clone(),
+            size_mb: size / (1024 * 1024),
+            sha256: None,
+            signature_url: None,
+            license: String::new(),
+        },
+    };
+    create_write_json_file(&format!("{partial_dir}/{DICT_META_FILENAME}"), meta)?;
+    install_dict_dir(abbr, &partial_dir).or(Err("error in installing dictionary"))?;
+    fs::remove_file(legacy_path).or(Err("error in deleting legacy dictionary"))?;
     Ok(())
 }
 
-fn create_write_json_file<T>(filename: &str, file_value: T) -> Result<(), String>
+pub fn open_write_json_payload<T>(filename: &str, payload: &str) -> Result<(), String>
 where
     T: Serialize + DeserializeOwned + std::fmt::Debug,
+{
+    let name = format!("{filename}.json");
+    let config_value = serde_json::from_str::<T>(payload).unwrap();
+
+    match File::options().write(true).truncate(true).open(&name) {
+        Ok(file) => serde_json::to_writer(file, &config_value).map_err(|err| err.to_string()),
+        Err(err) => {
+            eprintln!("open_write_json_payload: {err} ---> {name} is created");
+            create_write_json_file(filename, config_value)
+        }
+    }
+}
+
+pub fn create_write_json_file<T>(filename: &str, file_value: T) -> Result<(), String>
+where
+    T: Serialize,
 {
     let name = format!("{filename}.json");
     match File::create(name) {
@@ -262,101 +277,378 @@ where
                 Ok(())
             }
         }
-        Err(err) => return Err(err.to_string()),
+        Err(err) => Err(err.to_string()),
     }
 }
 
-/// Neglecting the time it takes to download a dictionary, in devmode it would take 340 seconds (< 6 min) to rectify a 320 MB file
-/// and write it to the storage.
-pub async fn download_dict(abbr: &str, window: tauri::Window) -> Result<(), String> {
-    let (t_once_x, r_once_x) = mpsc::channel::<()>();
-    let ev_han = window.once(format!("cancel_download_{abbr}"), move |_| {
-        if let Err(e) = t_once_x.send(()) {
-            eprintln!("{}", e.to_string());
+/// A value kept in `{path}.json` next to the settings, read on first use and written again after every change.
+pub struct JsonStore<T> {
+    path: String,
+    value: Mutex<Option<T>>,
+}
+
+impl<T: Serialize + DeserializeOwned + Default + std::fmt::Debug> JsonStore<T> {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            value: Mutex::new(None),
         }
+    }
+
+    /// Runs `change` on the value, which returns its result and whether it changed the value.
+    /// A missing or unreadable file gives the default value.
+    pub fn update<R>(&self, change: impl FnOnce(&mut T) -> (R, bool)) -> Result<R, String> {
+        let mut value = self.value.lock().unwrap();
+        let value = value.get_or_insert_with(|| read_json_file(&self.path).unwrap_or_default());
+        let (result, changed) = change(value);
+        if changed {
+            create_write_json_file(&self.path, &*value)?;
+        }
+        Ok(result)
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Reports the progress of building the `abbr` dictionary through `downloading` events and cancels
+/// the build on `cancel_download_{abbr}`. The returned handler is to be unlistened once the build ends.
+fn watch_build(
+    abbr: &str,
+    window: &tauri::Window,
+    first_phase: BuildPhase,
+) -> (BuildProgress, tauri::EventHandler) {
+    let progress = {
+        let (abbr, window) = (abbr.to_owned(), window.clone());
+        BuildProgress::new(first_phase, move |phase, percentage| {
+            let status = DictDowlonadStatus {
+                name: &abbr,
+                phase,
+                percentage: percentage as i8,
+            };
+            if let Err(e) = window.emit("downloading", status) {
+                eprintln!("error in emitting payload: {e}");
+            }
+        })
+    };
+    let canceled = progress.clone();
+    let ev_han = window.once(format!("cancel_download_{abbr}"), move |_| {
+        canceled.cancel()
     });
-    let value = OFFLINE_DICTS.get(&abbr).unwrap();
-    let res = CLIENT
-        .get(value.url)
-        .send()
+    (progress, ev_han)
+}
+
+/// Passes the downloaded bytes on to the converter running alongside the download, see `build_from_archive`.
+struct ConverterFeed {
+    chunks: mpsc::Sender<Vec<u8>>,
+    fed: u64,
+}
+
+impl ConverterFeed {
+    /// Waits while the converter is behind. Fails once it stopped, its error is reported when it is joined.
+    async fn feed(&mut self, chunk: &[u8]) -> Result<(), String> {
+        self.chunks
+            .send(chunk.to_vec())
+            .await
+            .or(Err(CONVERTER_STOPPED))?;
+        self.fed += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Catches up with the bytes an earlier `download_dict` left in `part_path`.
+    async fn feed_file(&mut self, part_path: &str, until: u64) -> Result<(), String> {
+        let (chunks, part_path, from) = (self.chunks.clone(), part_path.to_owned(), self.fed);
+        tauri::async_runtime::spawn_blocking(move || -> Result<(), String> {
+            let mut file = File::open(part_path).or(Err("error in reading tar.xz file"))?;
+            file.seek(SeekFrom::Start(from))
+                .or(Err("error in reading tar.xz file"))?;
+            let mut rest = file.take(until - from);
+            let mut buf = vec![0; 64 * 1024];
+            loop {
+                match rest
+                    .read(&mut buf)
+                    .or(Err("error in reading tar.xz file"))?
+                {
+                    0 => return Ok(()),
+                    n => chunks
+                        .blocking_send(buf[..n].to_vec())
+                        .or(Err(CONVERTER_STOPPED))?,
+                }
+            }
+        })
         .await
-        .or(Err("connection error"))?;
-    let total_size = res
-        .content_length()
-        .unwrap_or(value.length_mb * 1024 * 1024);
-    let mut stream = res.bytes_stream();
-    let tarxz_path = format!("{}/{}.tar.xz", CACHE_PATH_WITH_IDENTIFIER.to_string(), abbr);
-    let mut tarxz_dict_file = File::create(&tarxz_path).or(Err("error in creating tar.xz file"))?;
-    let mut downloaded: u64 = 0;
-    let emit_dl_status = |p: i8, _print_msg: &str| -> Result<(), String> {
-        // eprintln!("{_print_msg} {p}%");
-        window
-            .emit(
-                "downloading",
-                DictDowlonadStatus {
-                    name: abbr,
-                    percentage: p,
-                },
-            )
-            .or(Err("error in emitting payload"))?;
+        .or(Err("error in joining thread"))??;
+        self.fed = until;
         Ok(())
+    }
+}
+
+/// Downloads `url` into `part_path`, continuing from the bytes an earlier attempt left there with a
+/// `Range` request. Returns `false` when the connection dropped before the whole archive arrived,
+/// the partial file is then kept so that the next attempt, or the next `download_dict`, resumes it.
+async fn resume_download(
+    url: &str,
+    part_path: &str,
+    size_hint: u64,
+    progress: &BuildProgress,
+    converter: &mut ConverterFeed,
+) -> Result<bool, String> {
+    let mut offset = fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    let mut req = download_client().get(url);
+    if offset > 0 {
+        req = req.header(RANGE, format!("bytes={offset}-"));
+    }
+    let res = match req.send().await {
+        Ok(res) => res,
+        Err(_) => return Ok(false),
     };
-    let now = std::time::Instant::now();
-    let mut dur = std::time::Duration::new(2, 0);
-    emit_dl_status(0, &format!("downloading {abbr}")).unwrap(); // download started.
-    while let Some(Ok(chunk)) = stream.next().await {
-        if r_once_x.try_recv().is_ok() {
-            fs::remove_file(&tarxz_path).or(Err("error in deleting zip file"))?;
-            Err("download canceled")?
+    let file = match res.status() {
+        StatusCode::PARTIAL_CONTENT => {
+            converter.feed_file(part_path, offset).await?;
+            File::options().append(true).open(part_path)
         }
+        // the partial file is larger than the archive, it cannot be resumed.
+        StatusCode::RANGE_NOT_SATISFIABLE => {
+            fs::remove_file(part_path).or(Err("error in deleting zip file"))?;
+            return Ok(false);
+        }
+        // the server ignored the range, start over.
+        status if status.is_success() => {
+            offset = 0;
+            File::create(part_path)
+        }
+        _ => Err("connection error")?,
+    };
+    // the converter cannot rewind, what it has been fed must be where the download continues.
+    if converter.fed != offset {
+        Err("the download could not be resumed, try again")?
+    }
+    let mut tarxz_dict_file = file.or(Err("error in creating tar.xz file"))?;
+    let expected_size = res.content_length().map(|len| offset + len);
+    let total_size = expected_size.unwrap_or(size_hint).max(1);
+
+    let mut downloaded = offset;
+    let mut stream = res.bytes_stream();
+    progress.report(BuildPhase::Downloading, downloaded, total_size); // download resumed.
+    while let Some(chunk) = stream.next().await {
+        progress.check_canceled()?;
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => return Ok(false),
+        };
         tarxz_dict_file
             .write_all(&chunk)
             .or(Err("error in writing chunk"))?;
-        let len = chunk.len() as u64;
-        let new_percentage = (downloaded + len) * 100 / total_size;
-        downloaded += len;
-        if dur > now.elapsed() {
+        converter.feed(&chunk).await?;
+        downloaded += chunk.len() as u64;
+        progress.report(BuildPhase::Downloading, downloaded, total_size);
+    }
+    // without a `Content-Length` a truncated body cannot be told apart, it is caught by the decoder then.
+    Ok(downloaded >= expected_size.unwrap_or(downloaded))
+}
+
+/// Checks the downloaded archive against the digest of the catalog and, when the settings hold a
+/// `dictPublicKey`, against the minisign signature published at `signature_url`.
+async fn verify_download(part_path: &str, dict: &CatalogDict) -> Result<(), String> {
+    let signature = match (setting_str("dictPublicKey"), &dict.signature_url) {
+        (Some(public_key), Some(signature_url)) => {
+            let signature = client()
+                .get(signature_url)
+                .send()
+                .await
+                .and_then(|res| res.error_for_status())
+                .or(Err("connection error"))?
+                .text()
+                .await
+                .or(Err("error in reading signature"))?;
+            Some((public_key, signature))
+        }
+        (Some(_), None) => Err("dictionary is not signed")?,
+        (None, _) => None,
+    };
+    // hashing the whole archive takes a while, it is kept off the async runtime.
+    let (part_path, sha256) = (part_path.to_owned(), dict.sha256.clone());
+    tauri::async_runtime::spawn_blocking(move || {
+        let signature = signature
+            .as_ref()
+            .map(|(public_key, signature)| (public_key.as_str(), signature.as_str()));
+        verify_archive(&part_path, sha256.as_deref(), signature)
+    })
+    .await
+    .or(Err("error in joining thread"))?
+}
+
+/// Deletes what downloads of other versions of the `abbr` dictionary left, they can never be resumed.
+fn remove_stale_parts(abbr: &str, part_path: &str) {
+    let entries = match fs::read_dir(&*CACHE_PATH_WITH_IDENTIFIER) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        let stem = match path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".tar.xz.part"))
+        {
+            Some(stem) if stem.starts_with(&format!("{abbr}-")) => stem,
+            _ => continue,
+        };
+        // "zh-CN-1.0" is a download of zh-CN, not of zh.
+        let of_other_dict = stem
+            .match_indices('-')
+            .any(|(i, _)| i > abbr.len() && find_catalog_dict(&stem[..i]).is_some());
+        if of_other_dict || path == Path::new(part_path) {
             continue;
         }
-        emit_dl_status(new_percentage as i8, &format!("downloading {abbr}")).unwrap();
-        dur = std::time::Duration::from_secs(dur.as_secs() + 2);
+        if let Err(e) = fs::remove_file(&path) {
+            eprintln!("error in deleting {}: {e}", path.display());
+        }
     }
-    emit_dl_status(100, &format!("downloading {abbr}")).unwrap(); // download finished.
+}
 
-    let mut abs_json_dir = find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, JSON_DIR);
-    if fs::metadata(&abs_json_dir).is_err() {
-        fs::create_dir_all(&abs_json_dir).or(Err("error while creating nested directory."))?;
+/// The archive is converted while it is being downloaded, so neither it nor the unpacked dump is ever
+/// held in memory, and the dictionary is only installed once the whole archive has been verified.
+pub async fn download_dict(abbr: &str, window: tauri::Window) -> Result<(), String> {
+    let value = find_catalog_dict(abbr).ok_or("dictionary not found")?;
+    // the version is part of the name so that a partial download is never resumed with a newer archive.
+    let part_path = format!(
+        "{}/{abbr}-{}.tar.xz.part",
+        *CACHE_PATH_WITH_IDENTIFIER, value.version
+    );
+    remove_stale_parts(abbr, &part_path);
+    let partial_dir = format!("{}.partial", dict_dir(abbr));
+    let (progress, ev_han) = watch_build(abbr, &window, BuildPhase::Downloading);
+
+    let (chunks_tx, chunks_rx) = mpsc::channel(PIPELINE_CHUNKS);
+    let size_hint = value.size_mb * 1024 * 1024;
+    let converter = {
+        let (abbr, partial_dir, progress) =
+            (abbr.to_owned(), partial_dir.clone(), progress.clone());
+        thread::spawn(move || {
+            let chunks = ChunkReader::new(chunks_rx);
+            build_from_archive(chunks, size_hint, &abbr, &partial_dir, &progress)
+                .map_err(|e| e.to_string())
+        })
+    };
+    let mut feed = ConverterFeed {
+        chunks: chunks_tx,
+        fed: 0,
+    };
+    let mut downloaded = Err("download interrupted, try again to resume it".to_owned());
+    for _ in 0..MAX_DOWNLOAD_ATTEMPTS {
+        match resume_download(&value.url, &part_path, size_hint, &progress, &mut feed).await {
+            Ok(false) => continue,
+            Ok(true) => downloaded = Ok(()),
+            Err(e) => downloaded = Err(e),
+        }
+        break;
     }
-    let file = File::open(&tarxz_path).unwrap();
-    let decompressor = XzDecoder::new(file);
-    tar::Archive::new(decompressor)
-        .unpack(&abs_json_dir)
-        .or(Err("error in unpacking"))?;
-    let incorrect_file_path = format!("{abs_json_dir}/incorrect_{abbr}.json");
-    let mut unpacked_file =
-        File::open(&incorrect_file_path).or(Err("error in reading unpacked file"))?;
-    let mut contents = String::new();
-    unpacked_file.read_to_string(&mut contents).unwrap();
-    abs_json_dir.push_str(&format!("/{abbr}"));
-    if let Err(e) = rectify_incorrect_string(&contents, abbr, &abs_json_dir) {
-        return Err(e.to_string());
+    drop(feed); // ends the archive for the converter.
+    if downloaded.is_ok() {
+        progress.enter(BuildPhase::Extracting);
+    }
+    let converted = tauri::async_runtime::spawn_blocking(move || converter.join())
+        .await
+        .ok()
+        .and_then(|joined| joined.ok())
+        .ok_or("error in joining thread")?;
+    window.unlisten(ev_han);
+
+    // an interrupted download is kept to be resumed, a canceled or broken one is not.
+    let keep_download =
+        matches!(&downloaded, Err(e) if e != CONVERTER_STOPPED) && !progress.is_canceled();
+    let built = match downloaded {
+        Ok(()) if !progress.is_canceled() => {
+            verify_download(&part_path, &value).await.and(converted)
+        }
+        Ok(()) => Err(CANCELED.to_owned()),
+        // the reason the converter stopped is the one to report.
+        Err(e) if e == CONVERTER_STOPPED => converted.and(Err(e)),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = built {
+        if !keep_download && fs::metadata(&part_path).is_ok() {
+            fs::remove_file(&part_path).or(Err("error in deleting zip file"))?;
+        }
+        if fs::metadata(&partial_dir).is_ok() {
+            fs::remove_dir_all(&partial_dir).or(Err("error in deleting partial dictionary"))?;
+        }
+        return Err(e);
     }
-    fs::remove_file(tarxz_path).or(Err("error in deleting zip file"))?;
-    fs::remove_file(incorrect_file_path).or(Err("error in deleting incorrect file"))?;
+
+    create_write_json_file(&format!("{partial_dir}/{DICT_META_FILENAME}"), value)?;
+    install_dict_dir(abbr, &partial_dir).or(Err("error in installing dictionary"))?;
+    fs::remove_file(part_path).or(Err("error in deleting zip file"))?;
+    Ok(())
+}
+
+/// Builds an offline dictionary from a file on disk and installs it under `code` just like a downloaded one.
+/// The file is either the `.ifo` of a StarDict dictionary, the `.index` of a dictd database, or a
+/// Wiktextract JSONL dump as published by kaikki.org.
+pub fn import_dict(
+    path: &str,
+    code: &str,
+    name: &str,
+    window: tauri::Window,
+) -> Result<(), String> {
+    if !is_valid_abbr(code) {
+        Err("invalid language code")?
+    }
+    let file = File::open(path).or(Err("error in reading dictionary file"))?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let partial_dir = format!("{}.partial", dict_dir(code));
+    let (progress, ev_han) = watch_build(code, &window, BuildPhase::Converting);
+
+    let built = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("ifo") => build_from_stardict(Path::new(path), &partial_dir, &progress),
+        Some("index") => build_from_dictd(Path::new(path), &partial_dir, &progress),
+        _ => {
+            let reader = ProgressReader::new(file, progress.clone(), BuildPhase::Converting, size);
+            let reader = BufReader::with_capacity(IMPORT_BUFFER_SIZE, reader);
+            build_from_jsonl(reader, code, &partial_dir, &progress)
+        }
+    }
+    .map_err(|e| e.to_string());
     window.unlisten(ev_han);
+    if let Err(e) = built {
+        if fs::metadata(&partial_dir).is_ok() {
+            fs::remove_dir_all(&partial_dir).or(Err("error in deleting partial dictionary"))?;
+        }
+        return Err(match progress.is_canceled() {
+            true => CANCELED.to_owned(),
+            false => e,
+        });
+    }
+
+    let meta = CatalogDict {
+        code: code.to_owned(),
+        name: name.trim().to_owned(),
+        version: IMPORTED_VERSION.to_owned(),
+        url: path.to_owned(),
+        size_mb: size / (1024 * 1024),
+        sha256: None,
+        signature_url: None,
+        license: String::new(),
+    };
+    create_write_json_file(&format!("{partial_dir}/{DICT_META_FILENAME}"), meta)?;
+    install_dict_dir(code, &partial_dir).or(Err("error in installing dictionary"))?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{download_dict, rectify_incorrect_string};
+    use super::download_dict;
+    use crate::offline_dict::build_from_jsonl;
     use fast_image_resize as fr;
     use icns::{IconFamily, Image};
     use image::codecs::png::PngEncoder;
     use image::io::Reader as ImageReader;
     use image::{ColorType, ImageEncoder};
     use license::{Gfdl1_3OrLater, License};
-    use std::io::{Read, Write};
+    use std::io::Write;
     use std::{
         fs::File,
         io::{BufReader, BufWriter},
@@ -385,25 +677,18 @@ mod tests {
             .unwrap()
             .get_window("main")
             .unwrap();
-        let d = block_on(download_dict("fr", g)).unwrap();
-        assert_eq!((), d);
+        block_on(download_dict("fr", g)).unwrap();
     }
 
     #[test]
-    fn benchmark_rectification_writing_dict() {
+    fn benchmark_conversion_writing_dict() {
         println!("starting");
         let name = format!(
             "{}/incorrect_fr.json",
             tauri::api::path::download_dir().unwrap().to_str().unwrap()
         );
-        let mut file = File::open(name).unwrap();
-        let mut incorrect_string = String::new();
-        file.read_to_string(&mut incorrect_string).unwrap();
-        println!("file is stringified");
-
-        let corr =
-            rectify_incorrect_string(&mut incorrect_string, "fr", "rectified_test_fr").unwrap();
-        assert_eq!((), corr);
+        let file = BufReader::new(File::open(name).unwrap());
+        build_from_jsonl(file, "fr", "rectified_test_fr", &Default::default()).unwrap();
     }
 
     #[test]
@@ -424,7 +709,7 @@ mod tests {
         let file = std::fs::File::open("icons/icon.png").unwrap();
         let image = ico::IconImage::read_png(file).unwrap();
         icon_dir.add_entry(ico::IconDirEntry::encode(&image).unwrap());
-        let rgba = vec![std::u8::MAX; 4 * 16 * 16];
+        let rgba = vec![u8::MAX; 4 * 16 * 16];
         let image = ico::IconImage::from_rgba_data(16, 16, rgba);
         icon_dir.add_entry(ico::IconDirEntry::encode(&image).unwrap());
         // Finally, write the ICO file to disk:
@@ -0,0 +1,167 @@
+use crate::helper::{
+    find_absolute_path, setting_u64, unix_time, JsonStore, CACHE_PATH_WITH_IDENTIFIER,
+};
+use serde::{Deserialize, Serialize};
+
+pub static HISTORY_FILENAME: &str = "history";
+/// Lookups kept when `historyLimit` is not set in the settings, a limit of 0 turns the history off.
+const DEFAULT_HISTORY_LIMIT: usize = 1000;
+
+lazy_static! {
+    pub static ref HISTORY: History = History::new(find_absolute_path(
+        &CACHE_PATH_WITH_IDENTIFIER,
+        HISTORY_FILENAME
+    ));
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LookupSource {
+    Offline,
+    Online,
+    Selection,
+    Clipboard,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub word: String,
+    pub from: String,
+    /// `None` for offline lookups, a dictionary covers a single language.
+    pub to: Option<String>,
+    pub source: LookupSource,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+}
+
+/// The lookups made in the app, oldest first, kept in `{path}.json`.
+pub struct History {
+    entries: JsonStore<Vec<HistoryEntry>>,
+}
+
+/// The `historyLimit` setting, or `DEFAULT_HISTORY_LIMIT`.
+pub fn history_limit() -> usize {
+    setting_u64("historyLimit").map_or(DEFAULT_HISTORY_LIMIT, |limit| limit as usize)
+}
+
+impl History {
+    pub fn new(path: String) -> Self {
+        Self {
+            entries: JsonStore::new(path),
+        }
+    }
+
+    /// Records a lookup and drops the oldest ones beyond `limit`. Looking the same word up again
+    /// right after only refreshes the timestamp of the last entry.
+    pub fn record(
+        &self,
+        word: &str,
+        from: &str,
+        to: Option<&str>,
+        source: LookupSource,
+        limit: usize,
+    ) -> Result<(), String> {
+        let word = word.trim();
+        if word.is_empty() {
+            return Ok(());
+        }
+        self.entries.update(|entries| {
+            match entries.last_mut() {
+                Some(last)
+                    if last.word == word
+                        && last.from == from
+                        && last.to.as_deref() == to
+                        && last.source == source =>
+                {
+                    last.timestamp = unix_time()
+                }
+                last => {
+                    let id = last.map_or(0, |last| last.id + 1);
+                    entries.push(HistoryEntry {
+                        id,
+                        word: word.to_owned(),
+                        from: from.to_owned(),
+                        to: to.map(str::to_owned),
+                        source,
+                        timestamp: unix_time(),
+                    })
+                }
+            }
+            let overflow = entries.len().saturating_sub(limit);
+            entries.drain(..overflow);
+            ((), true)
+        })
+    }
+
+    /// The last `limit` lookups, newest first.
+    pub fn list(&self, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        self.search("", limit)
+    }
+
+    /// The last `limit` lookups whose word contains `query`, ignoring case, newest first.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<HistoryEntry>, String> {
+        let query = query.trim().to_lowercase();
+        self.entries.update(|entries| {
+            let found = entries
+                .iter()
+                .rev()
+                .filter(|entry| entry.word.to_lowercase().contains(&query))
+                .take(limit)
+                .cloned()
+                .collect();
+            (found, false)
+        })
+    }
+
+    pub fn delete(&self, id: u64) -> Result<(), String> {
+        self.entries.update(|entries| {
+            let len = entries.len();
+            entries.retain(|entry| entry.id != id);
+            ((), entries.len() != len)
+        })
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        self.entries.update(|entries| {
+            entries.clear();
+            ((), true)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, LookupSource};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn history_is_recorded_and_trimmed() {
+        let tmp = TempDir::new("history");
+        let path = tmp.path().join("history");
+        let path = path.to_str().unwrap().to_owned();
+        let history = History::new(path.clone());
+        history.clear().unwrap();
+        for word in ["Haus", "Haus", "house", "Maus", "home"] {
+            history
+                .record(word, "de", Some("en"), LookupSource::Online, 3)
+                .unwrap();
+        }
+        history
+            .record(" ", "de", None, LookupSource::Offline, 3)
+            .unwrap();
+
+        let words = |entries: Vec<super::HistoryEntry>| {
+            entries.into_iter().map(|e| e.word).collect::<Vec<String>>()
+        };
+        // a fresh store reads what the first one wrote.
+        let history = History::new(path.clone());
+        assert_eq!(words(history.list(10).unwrap()), ["home", "Maus", "house"]);
+        assert_eq!(words(history.search("HO", 10).unwrap()), ["home", "house"]);
+        let house = history.search("house", 1).unwrap()[0].id;
+        history.delete(house).unwrap();
+        assert_eq!(words(history.list(10).unwrap()), ["home", "Maus"]);
+        history.clear().unwrap();
+        assert!(history.list(10).unwrap().is_empty());
+    }
+}
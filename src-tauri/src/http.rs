@@ -0,0 +1,140 @@
+use crate::helper::{setting_str, setting_u64};
+use reqwest::{Client, Proxy};
+use std::{sync::RwLock, time::Duration};
+
+/// Used when `httpConnectTimeoutSecs` is not set.
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+
+lazy_static! {
+    static ref CLIENTS: RwLock<Clients> = RwLock::new(Clients::build(HttpSettings::read()));
+}
+
+/// The settings the HTTP clients are built from.
+#[derive(Clone, PartialEq, Default, Debug)]
+struct HttpSettings {
+    /// `httpProxy`, an `http://`, `https://` or `socks5://` URL all requests go through.
+    proxy: Option<String>,
+    /// `httpUserAgent`, reqwest's own when not set.
+    user_agent: Option<String>,
+    /// `httpTimeoutSecs`, the time a whole request may take, none when not set or 0.
+    timeout_secs: Option<u64>,
+    /// `httpConnectTimeoutSecs`.
+    connect_timeout_secs: u64,
+}
+
+impl HttpSettings {
+    fn read() -> Self {
+        Self {
+            proxy: setting_str("httpProxy"),
+            user_agent: setting_str("httpUserAgent"),
+            timeout_secs: setting_u64("httpTimeoutSecs").filter(|secs| *secs > 0),
+            connect_timeout_secs: setting_u64("httpConnectTimeoutSecs")
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS),
+        }
+    }
+}
+
+struct Clients {
+    settings: HttpSettings,
+    client: Client,
+    download: Client,
+}
+
+impl Clients {
+    /// Falls back to the default settings when the given ones cannot be applied, so that a mistyped
+    /// proxy does not keep the app from starting.
+    fn build(settings: HttpSettings) -> Self {
+        match Self::try_build(settings) {
+            Ok(clients) => clients,
+            Err(e) => {
+                eprintln!("error in applying http settings: {e}");
+                Self::try_build(HttpSettings {
+                    connect_timeout_secs: DEFAULT_CONNECT_TIMEOUT_SECS,
+                    ..Default::default()
+                })
+                .unwrap()
+            }
+        }
+    }
+
+    fn try_build(settings: HttpSettings) -> Result<Self, String> {
+        let client = builder(&settings)?;
+        let client = match settings.timeout_secs {
+            Some(secs) => client.timeout(Duration::from_secs(secs)),
+            None => client,
+        };
+        let client = client.build().map_err(|e| e.to_string())?;
+        // an archive takes longer than any request timeout, and is fetched by ranges that must
+        // match the bytes on disk, not a compressed stream of them.
+        let download = builder(&settings)?
+            .no_gzip()
+            .no_brotli()
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            settings,
+            client,
+            download,
+        })
+    }
+}
+
+fn builder(settings: &HttpSettings) -> Result<reqwest::ClientBuilder, String> {
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(settings.connect_timeout_secs))
+        .gzip(true)
+        .brotli(true)
+        .use_rustls_tls();
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy).or(Err("invalid proxy url"))?);
+    }
+    if let Some(user_agent) = &settings.user_agent {
+        builder = builder.user_agent(user_agent);
+    }
+    Ok(builder)
+}
+
+/// The client for requests to the online providers, TTS and the dictionary catalog.
+pub fn client() -> Client {
+    CLIENTS.read().unwrap().client.clone()
+}
+
+/// The client for dictionary downloads, it has no request timeout and asks for no compression.
+pub fn download_client() -> Client {
+    CLIENTS.read().unwrap().download.clone()
+}
+
+/// Rebuilds the clients when their settings have changed. Requests already sent keep the old ones.
+pub fn reload_clients() -> Result<(), String> {
+    let settings = HttpSettings::read();
+    if CLIENTS.read().unwrap().settings == settings {
+        return Ok(());
+    }
+    let clients = Clients::try_build(settings)?;
+    *CLIENTS.write().unwrap() = clients;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clients, HttpSettings};
+
+    #[test]
+    fn clients_are_built_from_settings() {
+        let settings = HttpSettings {
+            proxy: Some("socks5://127.0.0.1:1080".to_owned()),
+            user_agent: Some("tiny-beast".to_owned()),
+            timeout_secs: Some(20),
+            connect_timeout_secs: 5,
+        };
+        let clients = Clients::try_build(settings.clone()).unwrap();
+        assert_eq!(clients.settings, settings);
+
+        let mistyped = HttpSettings {
+            proxy: Some("127.0.0.1 1080".to_owned()),
+            ..settings
+        };
+        assert!(Clients::try_build(mistyped.clone()).is_err());
+        assert_eq!(Clients::build(mistyped).settings.proxy, None);
+    }
+}
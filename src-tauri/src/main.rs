@@ -7,12 +7,25 @@
 extern crate lazy_static;
 
 mod helper;
+mod history;
+mod http;
+mod offline_dict;
 mod online_translate;
 mod speaker;
+#[cfg(test)]
+mod test_dir;
+mod vocabulary;
 
 use helper::*;
+use history::{history_limit, HistoryEntry, LookupSource, HISTORY};
 use ijson::IValue;
-use online_translate::{OnlineTranslation, OnlineTranslator};
+use offline_dict::{
+    available_dicts, is_valid_abbr, refresh_catalog, AvailableDict, OfflineLookup, ReverseMatch,
+};
+use online_translate::{
+    supersede, OnlineDonePayload, OnlineTranslation, OnlineTranslator, ProviderResultPayload,
+    ONLINE_CACHE,
+};
 use rdev::{
     EventType::{ButtonRelease, KeyPress, KeyRelease, MouseMove, Wheel},
     Key::{Backspace, ControlLeft, ControlRight, Escape, KeyC, ShiftLeft, ShiftRight},
@@ -22,7 +35,7 @@ use std::{
     fs,
     str::FromStr,
     sync::{
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
         Arc, Mutex,
     },
     thread,
@@ -31,15 +44,23 @@ use tauri::{
     CustomMenuItem, Manager, PhysicalPosition, PhysicalSize, SystemTray, SystemTrayEvent,
     SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu, WindowEvent,
 };
+use vocabulary::{Card, DueCard, Review, VocabListSummary, VOCABULARY};
+
+const TRANSLATE_CLIPBOARD_TITLE: &str = "Clipboard";
+const TRANSLATE_SELECTED_TEXT_TITLE: &str = "Selected Text";
+const DEFAULT_THEME_TITLE: &str = "Default";
+const DEFAULT_THEME_ID: &str = "default";
+const DARK_THEME_TITLE: &str = "Pitch Black";
+const DARK_THEME_ID: &str = "dark";
+const LIGHT_THEME_TITLE: &str = "Sunset";
+const LIGHT_THEME_ID: &str = "light";
+const MAX_SUGGESTIONS: usize = 50;
+const MAX_REVERSE_MATCHES: usize = 30;
+const MAX_HISTORY_ENTRIES: usize = 500;
+const MAX_DUE_CARDS: usize = 100;
 
-const TRANSLATE_CLIPBOARD_TITLE: &'static str = "Clipboard";
-const TRANSLATE_SELECTED_TEXT_TITLE: &'static str = "Selected Text";
-const DEFAULT_THEME_TITLE: &'static str = "Default";
-const DEFAULT_THEME_ID: &'static str = "default";
-const DARK_THEME_TITLE: &'static str = "Pitch Black";
-const DARK_THEME_ID: &'static str = "dark";
-const LIGHT_THEME_TITLE: &'static str = "Sunset";
-const LIGHT_THEME_ID: &'static str = "light";
+/// Id of the next streamed online lookup.
+static NEXT_ONLINE_REQUEST: AtomicU64 = AtomicU64::new(0);
 
 fn toggle_menu_item_status(title: &str, status: bool) -> String {
     format!("{} {}", if status { "\u{25cf}" } else { "\u{25cb}" }, title)
@@ -74,6 +95,11 @@ fn main() {
         &CACHE_PATH_WITH_IDENTIFIER,
         SETTINGS_FILENAME,
     ));
+    // converting a large dictionary takes a while, lookups in it fail until it is done.
+    thread::spawn(|| {
+        restore_set_aside_dicts();
+        migrate_legacy_dicts();
+    });
 
     let mut default_theme = CustomMenuItem::new(
         DEFAULT_THEME_ID,
@@ -141,7 +167,7 @@ fn main() {
     fn consume_selected_text() -> Vec<u8> {
         let output = std::process::Command::new("xsel").output();
         if let Err(e) = output {
-            eprintln!("{}", e.to_string());
+            eprintln!("{e}");
             return vec![];
         }
         output.unwrap().stdout
@@ -154,14 +180,34 @@ fn main() {
     tauri::Builder::default()
         .invoke_handler(tauri::generate_handler![
             offline_translate,
+            offline_suggest,
+            offline_reverse_search,
             online_translate,
+            online_translate_stream,
             speak,
             download_dict,
             delete_dict,
+            import_dict_from_file,
+            list_available_dicts,
+            list_history,
+            search_history,
+            delete_history_entry,
+            clear_history,
+            list_vocab_lists,
+            create_vocab_list,
+            delete_vocab_list,
+            list_vocab_cards,
+            save_to_vocab,
+            annotate_vocab_card,
+            remove_vocab_card,
+            due_vocab_cards,
+            grade_vocab_card,
+            next_vocab_intervals,
+            clear_online_cache,
         ])
         .system_tray(tray)
-        .on_system_tray_event(move |app, event| match event {
-            SystemTrayEvent::MenuItemClick { id, .. } => {
+        .on_system_tray_event(move |app, event| {
+            if let SystemTrayEvent::MenuItemClick { id, .. } = event {
                 let item_handle = app.tray_handle().get_item(&id);
                 let window = app.get_window("main").unwrap();
 
@@ -244,7 +290,6 @@ fn main() {
                     _ => {}
                 }
             }
-            _ => {}
         })
         .setup(move |app| {
             let window = app.get_window("main").unwrap();
@@ -252,10 +297,7 @@ fn main() {
             if fs::metadata(CACHE_PATH_WITH_IDENTIFIER.to_string()).is_err() {
                 fs::create_dir_all(CACHE_PATH_WITH_IDENTIFIER.to_string())
                     .or(Err("error while creating base app directory.".to_string()))?;
-                println!(
-                    "base dir created: {}",
-                    CACHE_PATH_WITH_IDENTIFIER.to_string()
-                );
+                println!("base dir created: {}", *CACHE_PATH_WITH_IDENTIFIER);
             }
 
             let config_win = window.clone();
@@ -267,9 +309,13 @@ fn main() {
                 ) {
                     eprintln!(
                         "error in writing new config at {} : {e}",
-                        CACHE_PATH_WITH_IDENTIFIER.to_string()
+                        *CACHE_PATH_WITH_IDENTIFIER
                     );
                 } else {
+                    reload_settings();
+                    if let Err(e) = http::reload_clients() {
+                        eprintln!("error in applying http settings: {e}");
+                    }
                     config_win.emit("config_saved", "").unwrap();
                 }
             });
@@ -294,10 +340,7 @@ fn main() {
 
             window.listen("app_focused", move |ev| {
                 let is_focused = ev.payload().unwrap();
-                is_app_focused.store(
-                    if is_focused == "true" { true } else { false },
-                    std::sync::atomic::Ordering::Relaxed,
-                );
+                is_app_focused.store(is_focused == "true", std::sync::atomic::Ordering::Relaxed);
                 consume_selected_text();
             });
 
@@ -330,10 +373,8 @@ fn main() {
                     let clone_arc = Arc::clone(&arc_op);
                     let b = builder.to_owned();
                     builder.on_window_event(move |e| match e {
-                        WindowEvent::Focused(is_focused) => {
-                            if !*is_focused {
-                                b.hide().unwrap();
-                            }
+                        WindowEvent::Focused(false) => {
+                            b.hide().unwrap();
                         }
                         WindowEvent::CloseRequested { api, .. } => {
                             api.prevent_close();
@@ -342,7 +383,7 @@ fn main() {
                                 thread_win.app_handle().tray_handle().get_item("show_hide");
                             item_handle.set_title("Hide").unwrap(); // in front-end window.show() is called.
                             if let Err(err) = thread_win.emit("text_selected", output) {
-                                eprintln!("{}", err.to_string());
+                                eprintln!("{err}");
                             }
                             b.hide().unwrap();
                         }
@@ -392,7 +433,6 @@ fn main() {
                             }
                             KeyRelease(ShiftLeft) | KeyRelease(ShiftRight) => {
                                 consume_selected_text(); // consume text selected using shift keys as it's better to ignore such selections.
-                                ()
                             }
                             KeyRelease(Escape) | KeyRelease(Backspace) | Wheel { .. } => {
                                 builder.hide().unwrap();
@@ -428,40 +468,152 @@ fn main() {
         .expect("error while running tauri application");
 }
 
+/// Records a successful lookup. Lookups without a `source` are the ones made while typing, they
+/// are not recorded so that half-typed words stay out of the history.
+fn record_lookup(word: &str, from: &str, to: Option<&str>, source: Option<LookupSource>) {
+    let source = match source {
+        Some(source) => source,
+        None => return,
+    };
+    if let Err(e) = HISTORY.record(word, from, to, source, history_limit()) {
+        eprintln!("history error: {e}");
+    }
+}
+
+/// Results that could not be cached are still shown, the error is only worth a note.
+fn log_cache_error(translation: &OnlineTranslation) {
+    if let Some(e) = translation.cache_error() {
+        eprintln!("online cache error: {e}");
+    }
+}
+
 #[tauri::command]
-async fn offline_translate(word: &str, lang: &str) -> Result<IValue, String> {
-    let selected_lang;
-    let word = word.trim().to_lowercase();
-
-    match lang {
-        "en" => selected_lang = EN_DICT.as_ref(),
-        "fr" => selected_lang = FR_DICT.as_ref(),
-        "de" => selected_lang = DE_DICT.as_ref(),
-        "es" => selected_lang = ES_DICT.as_ref(),
-        "it" => selected_lang = IT_DICT.as_ref(),
-        "fa" => selected_lang = FA_DICT.as_ref(),
-        "pt" => selected_lang = PT_DICT.as_ref(),
-        "zh-CN" => selected_lang = ZH_CN_DICT.as_ref(),
-        "ar" => selected_lang = AR_DICT.as_ref(),
-        _ => return Err("language not found".to_string()),
+async fn offline_translate(
+    word: &str,
+    lang: &str,
+    source: Option<LookupSource>,
+) -> Result<OfflineLookup, String> {
+    let selected_lang = LOADED_DICTS.get(lang)?;
+    match selected_lang.lookup(word) {
+        Ok(Some(found)) => {
+            if found.found() {
+                record_lookup(word, lang, None, source);
+            }
+            Ok(found)
+        }
+        Ok(None) => Err("not found".to_string()),
+        Err(e) => Err(e.to_string()),
     }
+}
+
+#[tauri::command]
+async fn offline_suggest(prefix: &str, lang: &str, limit: usize) -> Result<Vec<String>, String> {
+    let selected_lang = LOADED_DICTS.get(lang)?;
+    Ok(selected_lang.suggest(prefix, limit.min(MAX_SUGGESTIONS)))
+}
+
+#[tauri::command]
+async fn offline_reverse_search(query: &str, lang: &str) -> Result<Vec<ReverseMatch>, String> {
+    let selected_lang = LOADED_DICTS.get(lang)?;
+    selected_lang
+        .reverse_search(query, MAX_REVERSE_MATCHES)
+        .map_err(|e| e.to_string())
+}
 
-    if let Some(found) = selected_lang?.get(&*word) {
-        let val = found.to_owned();
-        Ok(val)
-    } else {
-        Err("not found".to_string())
+#[tauri::command]
+async fn list_available_dicts() -> Vec<AvailableDict> {
+    if let Err(e) = refresh_catalog().await {
+        eprintln!("catalog refresh error: {e}");
     }
+    available_dicts()
 }
 
 #[tauri::command]
-async fn online_translate(from: &str, to: &str, word: &str) -> Result<OnlineTranslation, String> {
+async fn online_translate(
+    from: &str,
+    to: &str,
+    word: &str,
+    source: Option<LookupSource>,
+    window: tauri::Window,
+) -> Result<OnlineTranslation, String> {
     let translator_struct = OnlineTranslator { from, to };
-    translator_struct.translate(&word).await
+    let translation = supersede(window.label(), translator_struct.translate(word))
+        .await
+        .ok_or("superseded by a newer lookup")?;
+    log_cache_error(&translation);
+    if translation.found() {
+        record_lookup(word, from, Some(to), source);
+    }
+    Ok(translation)
+}
+
+/// Starts an online lookup and returns its id at once. The outcome of each provider is sent in an
+/// `online_result` event as soon as it answers, and an `online_done` event follows the last one.
+/// A newer lookup from the same window cancels this one, and no `online_done` is sent for it.
+#[tauri::command]
+fn online_translate_stream(
+    from: String,
+    to: String,
+    word: String,
+    source: Option<LookupSource>,
+    window: tauri::Window,
+) -> u64 {
+    let request_id = NEXT_ONLINE_REQUEST.fetch_add(1, Ordering::Relaxed);
+    tauri::async_runtime::spawn(async move {
+        let translator_struct = OnlineTranslator {
+            from: &from,
+            to: &to,
+        };
+        let lookup = translator_struct.translate_each(&word, |provider, result| {
+            let payload = ProviderResultPayload::new(request_id, provider, result);
+            if let Err(e) = window.emit("online_result", payload) {
+                eprintln!("error in emitting payload: {e}");
+            }
+        });
+        let translation = match supersede(window.label(), lookup).await {
+            Some(translation) => translation,
+            None => return,
+        };
+        log_cache_error(&translation);
+        let found = translation.found();
+        if found {
+            record_lookup(&word, &from, Some(&to), source);
+        }
+        let payload = OnlineDonePayload { request_id, found };
+        if let Err(e) = window.emit("online_done", payload) {
+            eprintln!("error in emitting payload: {e}");
+        }
+    });
+    request_id
+}
+
+#[tauri::command]
+async fn clear_online_cache() -> Result<(), String> {
+    ONLINE_CACHE.clear().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_history(limit: Option<usize>) -> Result<Vec<HistoryEntry>, String> {
+    HISTORY.list(limit.unwrap_or(MAX_HISTORY_ENTRIES))
+}
+
+#[tauri::command]
+async fn search_history(query: &str, limit: Option<usize>) -> Result<Vec<HistoryEntry>, String> {
+    HISTORY.search(query, limit.unwrap_or(MAX_HISTORY_ENTRIES))
+}
+
+#[tauri::command]
+async fn delete_history_entry(id: u64) -> Result<(), String> {
+    HISTORY.delete(id)
 }
 
 #[tauri::command]
-async fn speak<'a>(word: String, lang: String) {
+async fn clear_history() -> Result<(), String> {
+    HISTORY.clear()
+}
+
+#[tauri::command]
+async fn speak(word: String, lang: String) {
     if word.is_empty() {
         return;
     }
@@ -480,16 +632,109 @@ async fn speak<'a>(word: String, lang: String) {
 async fn download_dict(abbr: &str, app_window: tauri::Window) -> Result<(), String> {
     let window = app_window.get_window("main").unwrap();
     helper::download_dict(abbr, window).await?;
-    Ok(())
+    LOADED_DICTS.reload(abbr)
+}
+
+#[tauri::command]
+async fn import_dict_from_file(
+    path: &str,
+    lang_code: &str,
+    display_name: &str,
+    app_window: tauri::Window,
+) -> Result<(), String> {
+    let window = app_window.get_window("main").unwrap();
+    let (path, code, name) = (
+        path.to_owned(),
+        lang_code.to_owned(),
+        display_name.to_owned(),
+    );
+    // converting a large dump takes minutes, it must not hold up a runtime worker.
+    tauri::async_runtime::spawn_blocking(move || helper::import_dict(&path, &code, &name, window))
+        .await
+        .or(Err("error in joining thread"))??;
+    LOADED_DICTS.reload(lang_code)
 }
 
 #[tauri::command]
 async fn delete_dict(abbr: &str) -> Result<(), String> {
-    if let Err(e) = delete_json_file(&find_absolute_path(
+    if !is_valid_abbr(abbr) {
+        return Err("language not found".to_string());
+    }
+    LOADED_DICTS.unload(abbr);
+    // dictionaries downloaded by older versions are single json files.
+    let legacy = delete_json_file(&find_absolute_path(
         &CACHE_PATH_WITH_IDENTIFIER,
         &format!("{JSON_DIR}/{abbr}"),
-    )) {
-        return Err(e.to_string());
+    ));
+    if let Err(e) = delete_dict_dir(abbr) {
+        if legacy.is_err() {
+            return Err(e.to_string());
+        }
     }
     Ok(())
 }
+
+#[tauri::command]
+async fn list_vocab_lists() -> Result<Vec<VocabListSummary>, String> {
+    VOCABULARY.lists()
+}
+
+#[tauri::command]
+async fn create_vocab_list(name: &str) -> Result<(), String> {
+    VOCABULARY.create_list(name)
+}
+
+#[tauri::command]
+async fn delete_vocab_list(name: &str) -> Result<(), String> {
+    VOCABULARY.delete_list(name)
+}
+
+#[tauri::command]
+async fn list_vocab_cards(list: &str) -> Result<Vec<Card>, String> {
+    VOCABULARY.cards(list)
+}
+
+#[tauri::command]
+async fn save_to_vocab(
+    list: &str,
+    word: &str,
+    lang: &str,
+    entry: IValue,
+    notes: &str,
+    tags: Vec<String>,
+) -> Result<Card, String> {
+    VOCABULARY.save(list, word, lang, entry, notes, tags)
+}
+
+#[tauri::command]
+async fn annotate_vocab_card(
+    list: &str,
+    id: u64,
+    notes: &str,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    VOCABULARY.annotate(list, id, notes, tags)
+}
+
+#[tauri::command]
+async fn remove_vocab_card(list: &str, id: u64) -> Result<(), String> {
+    VOCABULARY.remove(list, id)
+}
+
+#[tauri::command]
+async fn due_vocab_cards(
+    list: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<DueCard>, String> {
+    VOCABULARY.due(list.as_deref(), limit.unwrap_or(MAX_DUE_CARDS))
+}
+
+#[tauri::command]
+async fn grade_vocab_card(list: &str, id: u64, grade: u8) -> Result<Review, String> {
+    VOCABULARY.grade(list, id, grade)
+}
+
+#[tauri::command]
+async fn next_vocab_intervals(list: &str, id: u64) -> Result<Vec<u32>, String> {
+    VOCABULARY.next_intervals(list, id)
+}
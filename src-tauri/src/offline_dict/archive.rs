@@ -0,0 +1,74 @@
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use std::{fs::File, io::Read};
+
+/// Checks a downloaded archive against the SHA-256 digest of the catalog and a minisign
+/// `(public key, detached signature)` pair, each when given. Both are computed in a single read.
+pub fn verify_archive(
+    path: &str,
+    sha256: Option<&str>,
+    signature: Option<(&str, &str)>,
+) -> Result<(), String> {
+    let verifier = match signature {
+        Some((public_key, signature)) => {
+            let public_key =
+                PublicKey::from_base64(public_key).or(Err("invalid dictionary public key"))?;
+            let signature = Signature::decode(signature).or(Err("invalid dictionary signature"))?;
+            Some((public_key, signature))
+        }
+        None => None,
+    };
+    let mut stream = match &verifier {
+        Some((public_key, signature)) => Some(
+            public_key
+                .verify_stream(signature)
+                .or(Err("unsupported dictionary signature"))?,
+        ),
+        None => None,
+    };
+
+    let mut file = File::open(path).or(Err("error in reading archive"))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).or(Err("error in reading archive"))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        if let Some(stream) = &mut stream {
+            stream.update(&buf[..n]);
+        }
+    }
+
+    let digest = format!("{:x}", hasher.finalize());
+    if let Some(expected) = sha256 {
+        if !digest.eq_ignore_ascii_case(expected.trim()) {
+            Err("checksum mismatch, the dictionary archive is corrupted")?
+        }
+    }
+    if let Some(mut stream) = stream {
+        stream.finalize().or(Err(
+            "invalid signature, the dictionary archive was tampered with",
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::verify_archive;
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn checksum_is_verified() {
+        let tmp = TempDir::new("checksum");
+        let path = tmp.path().join("archive.tar.xz");
+        std::fs::write(&path, b"abc").unwrap();
+        let path = path.to_str().unwrap();
+        let digest = "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD";
+        assert!(verify_archive(path, Some(digest), None).is_ok());
+        assert!(verify_archive(path, Some(&digest.replace('A', "B")), None).is_err());
+        assert!(verify_archive(path, None, None).is_ok());
+    }
+}
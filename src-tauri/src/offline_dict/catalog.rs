@@ -0,0 +1,161 @@
+use super::is_valid_abbr;
+use crate::helper::{
+    dict_dir, find_absolute_path, read_json_file, setting_str, CACHE_PATH_WITH_IDENTIFIER, JSON_DIR,
+};
+use crate::http::client;
+use serde::{Deserialize, Serialize};
+use std::{fs, sync::RwLock};
+
+static CATALOG_FILENAME: &str = "catalog";
+/// Written next to an installed dictionary, it is the catalog entry it was installed from.
+pub static DICT_META_FILENAME: &str = "meta";
+/// Catalogs of another format are ignored, the bundled one is used instead.
+const CATALOG_FORMAT: u32 = 1;
+static BUNDLED_CATALOG: &str = include_str!("../../catalog.json");
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Catalog {
+    pub format: u32,
+    pub dictionaries: Vec<CatalogDict>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CatalogDict {
+    pub code: String,
+    /// Language name shown for the dictionary, e.g. "English". Entries are picked by their `lang_code`.
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub size_mb: u64,
+    /// Hex-encoded SHA-256 digest of the archive at `url`, as printed by `build-dict`.
+    /// Archives of entries without one are only checked against their signature, if any.
+    pub sha256: Option<String>,
+    /// URL of a minisign signature of the archive, checked against `dictPublicKey` in the settings.
+    #[serde(default)]
+    pub signature_url: Option<String>,
+    pub license: String,
+}
+
+#[derive(Serialize)]
+pub struct AvailableDict {
+    #[serde(flatten)]
+    pub dict: CatalogDict,
+    pub installed_version: Option<String>,
+}
+
+lazy_static! {
+    static ref CATALOG: RwLock<Catalog> = RwLock::new(load_local_catalog());
+}
+
+fn catalog_path() -> String {
+    find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, CATALOG_FILENAME)
+}
+
+fn parse_catalog(json: &str) -> Result<Catalog, String> {
+    let catalog = serde_json::from_str::<Catalog>(json).map_err(|e| e.to_string())?;
+    if catalog.format != CATALOG_FORMAT {
+        return Err(format!(
+            "catalog format {} is not supported",
+            catalog.format
+        ));
+    }
+    if let Some(dict) = catalog
+        .dictionaries
+        .iter()
+        .find(|d| !is_valid_abbr(&d.code))
+    {
+        return Err(format!("invalid dictionary code {}", dict.code));
+    }
+    Ok(catalog)
+}
+
+/// The last catalog fetched from `dictCatalogUrl`, or the one bundled with the app.
+fn load_local_catalog() -> Catalog {
+    match read_json_file::<Catalog>(&catalog_path()) {
+        Ok(catalog) if catalog.format == CATALOG_FORMAT => catalog,
+        _ => parse_catalog(BUNDLED_CATALOG).unwrap(),
+    }
+}
+
+/// Fetches the catalog from `dictCatalogUrl` in the settings, if set, and keeps a copy on disk
+/// so it survives restarts and offline starts.
+pub async fn refresh_catalog() -> Result<(), String> {
+    let url = match setting_str("dictCatalogUrl") {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let body = client()
+        .get(url)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .or(Err("connection error"))?
+        .text()
+        .await
+        .or(Err("error in reading catalog"))?;
+    let catalog = parse_catalog(&body)?;
+    fs::write(format!("{}.json", catalog_path()), &body).or(Err("error in writing catalog"))?;
+    *CATALOG.write().unwrap() = catalog;
+    Ok(())
+}
+
+pub fn find_catalog_dict(code: &str) -> Option<CatalogDict> {
+    CATALOG
+        .read()
+        .unwrap()
+        .dictionaries
+        .iter()
+        .find(|dict| dict.code == code)
+        .cloned()
+}
+
+pub fn installed_dict_meta(code: &str) -> Option<CatalogDict> {
+    read_json_file::<CatalogDict>(&format!("{}/{DICT_META_FILENAME}", dict_dir(code))).ok()
+}
+
+/// Dictionaries installed without a catalog entry, e.g. imported from a local dump.
+fn uncataloged_dicts(catalog: &Catalog) -> Vec<CatalogDict> {
+    let entries = match fs::read_dir(find_absolute_path(&CACHE_PATH_WITH_IDENTIFIER, JSON_DIR)) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut dicts = entries
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|code| is_valid_abbr(code) && !catalog.dictionaries.iter().any(|d| &d.code == code))
+        .filter_map(|code| installed_dict_meta(&code))
+        .collect::<Vec<CatalogDict>>();
+    dicts.sort_by(|a, b| a.name.cmp(&b.name));
+    dicts
+}
+
+pub fn available_dicts() -> Vec<AvailableDict> {
+    let catalog = CATALOG.read().unwrap();
+    let mut dicts = catalog
+        .dictionaries
+        .iter()
+        .map(|dict| AvailableDict {
+            dict: dict.clone(),
+            installed_version: installed_dict_meta(&dict.code).map(|meta| meta.version),
+        })
+        .collect::<Vec<AvailableDict>>();
+    dicts.extend(
+        uncataloged_dicts(&catalog)
+            .into_iter()
+            .map(|dict| AvailableDict {
+                installed_version: Some(dict.version.clone()),
+                dict,
+            }),
+    );
+    dicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_catalog, BUNDLED_CATALOG};
+
+    #[test]
+    fn bundled_catalog_is_valid() {
+        let catalog = parse_catalog(BUNDLED_CATALOG).unwrap();
+        assert!(catalog.dictionaries.iter().any(|dict| dict.code == "en"));
+    }
+}
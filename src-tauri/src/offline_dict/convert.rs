@@ -0,0 +1,365 @@
+use super::{BuildPhase, BuildProgress, DictIndexBuilder, ProgressReader, CANCELED};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fmt,
+    io::{self, BufRead, BufReader, Read},
+    mem,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+use tokio::sync::mpsc as tokio_mpsc;
+use xz::read::XzDecoder;
+
+const BATCH_LINES: usize = 1024;
+/// Batches waiting to be parsed or indexed, per worker. Together with `BATCH_LINES` it bounds
+/// the number of lines held in memory.
+const QUEUED_BATCHES: usize = 2;
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The same for every entry of a dictionary, so they are left out of the index.
+static SHARED_FIELDS: &[&str] = &["lang", "lang_code"];
+
+type Batch<T> = (usize, Vec<T>);
+
+/// Reads the chunks sent through a channel as one stream, which ends once the sender is dropped.
+/// The sending side is async, so that a download never blocks the runtime waiting for the converter.
+pub struct ChunkReader {
+    chunks: tokio_mpsc::Receiver<Vec<u8>>,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl ChunkReader {
+    pub fn new(chunks: tokio_mpsc::Receiver<Vec<u8>>) -> Self {
+        Self {
+            chunks,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.chunk.len() - self.pos);
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Splits a Wiktextract line into its headword and the entry without the shared fields.
+/// `None` for lines that are not an entry, or an entry of another language than `code`.
+fn convert_line(line: &[u8], code: &str) -> Option<(String, Vec<u8>)> {
+    let mut entry = serde_json::from_slice::<Map<String, Value>>(line).ok()?;
+    match entry.get("lang_code") {
+        Some(Value::String(lang_code)) if lang_code != code => return None,
+        _ => {}
+    }
+    let word = match entry.remove("word")? {
+        Value::String(word) => word,
+        _ => return None,
+    };
+    for field in SHARED_FIELDS {
+        entry.remove(*field);
+    }
+    Some((word, serde_json::to_vec(&entry).ok()?))
+}
+
+/// Sends the lines of `reader` in numbered batches, until it ends, the build is canceled or nobody
+/// is receiving anymore.
+fn read_batches<R: BufRead>(
+    reader: &mut R,
+    batches: &mpsc::SyncSender<Batch<Vec<u8>>>,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut batch = Vec::with_capacity(BATCH_LINES);
+    let mut batch_number = 0;
+    loop {
+        let mut line = Vec::new();
+        let ended = reader.read_until(b'\n', &mut line)? == 0;
+        if !ended {
+            batch.push(line);
+        }
+        if batch.len() == BATCH_LINES || (ended && !batch.is_empty()) {
+            progress.check_canceled()?;
+            if batches.send((batch_number, mem::take(&mut batch))).is_err() {
+                return Ok(());
+            }
+            batch_number += 1;
+        }
+        if ended {
+            return Ok(());
+        }
+    }
+}
+
+/// Builds a dictionary into `dir` from the entries of `code` in a Wiktextract JSONL dump, one entry
+/// per line. Dumps of all languages hold the entries of other languages too, they are skipped.
+///
+/// A worker per core parses the lines in batches while the batches before them are indexed in
+/// input order, so only a few batches are in memory at any time, whatever the size of the dump.
+pub fn build_from_jsonl<R: BufRead>(
+    mut reader: R,
+    code: &str,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let workers = thread::available_parallelism().map_or(4, |n| n.get());
+    let mut builder = DictIndexBuilder::new(dir)?.with_progress(progress.clone());
+    let (batches_tx, batches_rx) = mpsc::sync_channel(workers * QUEUED_BATCHES);
+    let (converted_tx, converted_rx) = mpsc::sync_channel(workers * QUEUED_BATCHES);
+    // the receiver is dropped with the last worker, so reading stops when the workers do.
+    let batches_rx = Arc::new(Mutex::new(batches_rx));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let batches_rx = Arc::clone(&batches_rx);
+            let converted_tx = converted_tx.clone();
+            scope.spawn(move || loop {
+                let batch = batches_rx.lock().unwrap().recv();
+                let (batch_number, lines): Batch<Vec<u8>> = match batch {
+                    Ok(batch) => batch,
+                    Err(_) => break,
+                };
+                let entries = lines
+                    .iter()
+                    .filter_map(|line| convert_line(line, code))
+                    .collect();
+                if converted_tx.send((batch_number, entries)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(batches_rx);
+        drop(converted_tx);
+
+        let indexer = scope.spawn(|| -> Result<(), Box<dyn Error + Send + Sync>> {
+            let mut pending = BTreeMap::<usize, Vec<(String, Vec<u8>)>>::new();
+            let mut next = 0;
+            for (batch_number, entries) in converted_rx {
+                pending.insert(batch_number, entries);
+                while let Some(entries) = pending.remove(&next) {
+                    for (word, entry) in entries {
+                        builder.push(&word, &entry)?;
+                    }
+                    next += 1;
+                }
+            }
+            Ok(())
+        });
+
+        let read = read_batches(&mut reader, &batches_tx, progress);
+        drop(batches_tx);
+        indexer.join().or(Err("error in joining thread"))??;
+        read
+    })?;
+
+    if builder.is_empty() {
+        Err(format!(
+            "no dictionary entries of the language code {code} found"
+        ))?
+    }
+    progress.enter(BuildPhase::Indexing);
+    builder.finish()
+}
+
+/// Builds a dictionary into `dir` from a `.tar.xz` archive of `size` bytes holding the dump of `code`
+/// as `incorrect_{code}.json`. Nothing is unpacked to disk, the dump is converted as it is decompressed,
+/// so reading the archive is reported as `BuildPhase::Extracting`.
+pub fn build_from_archive<R: Read>(
+    reader: R,
+    size: u64,
+    code: &str,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let reader = ProgressReader::new(reader, progress.clone(), BuildPhase::Extracting, size);
+    let mut archive = tar::Archive::new(XzDecoder::new(reader));
+    let built = build_from_entries(&mut archive, code, dir, progress).and_then(|_| {
+        // reading the archive to its end lets the decoder check the integrity of the whole stream.
+        io::copy(&mut archive.into_inner(), &mut io::sink())?;
+        Ok(())
+    });
+    // the error of whichever stage noticed the cancellation first is not telling.
+    match built {
+        Err(_) if progress.is_canceled() => Err(CANCELED)?,
+        built => built,
+    }
+}
+
+fn build_from_entries<R: Read>(
+    archive: &mut tar::Archive<R>,
+    code: &str,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let filename = format!("incorrect_{code}.json");
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type().is_file()
+            && entry.path()?.file_name() == Some(OsStr::new(&filename))
+        {
+            let reader = BufReader::with_capacity(READ_BUFFER_SIZE, entry);
+            return build_from_jsonl(reader, code, dir, progress);
+        }
+    }
+    Err(format!("{filename} is missing from the archive"))?
+}
+
+/// Indexes the entries of a legacy dictionary as they are parsed.
+struct LegacyEntries<'a> {
+    builder: &'a mut DictIndexBuilder,
+    progress: &'a BuildProgress,
+}
+
+impl<'de> Visitor<'de> for LegacyEntries<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object of dictionary entries")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut entries: A) -> Result<(), A::Error> {
+        while let Some((word, entry)) = entries.next_entry::<String, Map<String, Value>>()? {
+            self.progress.check_canceled().map_err(de::Error::custom)?;
+            let entry = serde_json::to_vec(&entry).map_err(de::Error::custom)?;
+            self.builder
+                .push(&word, &entry)
+                .map_err(de::Error::custom)?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds a dictionary into `dir` from the single json object older versions kept a dictionary in,
+/// which maps every headword to an entry already without the shared fields. The object is parsed
+/// one entry at a time, so it is never held in memory as a whole.
+pub fn build_from_legacy_json<R: Read>(
+    reader: R,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut builder = DictIndexBuilder::new(dir)?.with_progress(progress.clone());
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_map(LegacyEntries {
+        builder: &mut builder,
+        progress,
+    })?;
+    deserializer.end()?;
+    if builder.is_empty() {
+        Err("no dictionary entries found")?
+    }
+    progress.enter(BuildPhase::Indexing);
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_from_archive, build_from_jsonl, build_from_legacy_json, ChunkReader, BATCH_LINES,
+    };
+    use crate::offline_dict::{BuildPhase, BuildProgress, DictIndex, CANCELED};
+    use crate::test_dir::TempDir;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn jsonl_dump_is_converted_in_order() {
+        let tmp = TempDir::new("convert");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut dump = String::new();
+        for i in 0..BATCH_LINES * 3 {
+            dump += &format!(
+                "{{\"word\": \"w{}\", \"lang\": \"English\", \"lang_code\": \"en\", \"pos\": \"noun\", \"n\": {i}}}\n",
+                i % 7
+            );
+        }
+        dump +=
+            "{\"word\": \"say \\\"hi\\\"\\\\\", \"lang\": \"English\", \"lang_code\": \"en\"}\n";
+        dump += "{\"word\": \"w3\", \"lang\": \"Translingual\", \"lang_code\": \"mul\"}\n";
+        dump += "not json\n\n";
+        build_from_jsonl(dump.as_bytes(), "en", dir, &Default::default()).unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        let entries = index.get("w3").unwrap().unwrap();
+        let numbers = entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.get("n").unwrap().to_i64().unwrap())
+            .collect::<Vec<i64>>();
+        assert_eq!(numbers.len(), (BATCH_LINES * 3 + 3) / 7);
+        assert!(numbers.windows(2).all(|w| w[0] < w[1]));
+        assert!(entries.as_array().unwrap()[0].get("lang").is_none());
+        assert!(index.get("say \"hi\"\\").unwrap().is_some());
+    }
+
+    #[test]
+    fn legacy_json_is_converted() {
+        let tmp = TempDir::new("convert_legacy");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let legacy = r#"{"house": {"pos": "noun", "n": 1}, "run": {"pos": "verb"}, "house": {"pos": "verb", "n": 2}}"#;
+        build_from_legacy_json(legacy.as_bytes(), dir, &Default::default()).unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        let entries = index.get("house").unwrap().unwrap();
+        assert_eq!(entries.as_array().unwrap().len(), 2);
+        assert!(index.get("run").unwrap().is_some());
+        assert!(build_from_legacy_json(&b"[]"[..], dir, &Default::default()).is_err());
+        assert!(build_from_legacy_json(&b"{}"[..], dir, &Default::default()).is_err());
+    }
+
+    #[test]
+    fn archive_is_converted_from_chunks() {
+        let dump = b"{\"word\": \"Haus\", \"lang\": \"German\", \"lang_code\": \"de\"}\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(dump.len() as u64);
+        header.set_mode(0o644);
+        let mut tar = tar::Builder::new(xz::write::XzEncoder::new(Vec::new(), 6));
+        tar.append_data(&mut header, "incorrect_de.json", &dump[..])
+            .unwrap();
+        let tarxz = tar.into_inner().unwrap().finish().unwrap();
+
+        let tmp = TempDir::new("convert_archive");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let (chunks_tx, chunks_rx) = tokio::sync::mpsc::channel(1);
+        let size = tarxz.len() as u64;
+        let sender = std::thread::spawn(move || {
+            for chunk in tarxz.chunks(7) {
+                chunks_tx.blocking_send(chunk.to_vec()).unwrap();
+            }
+        });
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let reported = Arc::clone(&phases);
+        let progress = BuildProgress::new(BuildPhase::Extracting, move |phase, percentage| {
+            reported.lock().unwrap().push((phase, percentage))
+        });
+        build_from_archive(ChunkReader::new(chunks_rx), size, "de", dir, &progress).unwrap();
+        sender.join().unwrap();
+        assert!(DictIndex::open(dir).unwrap().get("Haus").unwrap().is_some());
+        let phases = phases.lock().unwrap();
+        assert_eq!(phases.first(), Some(&(BuildPhase::Extracting, 0)));
+        assert_eq!(phases.last(), Some(&(BuildPhase::Indexing, 100)));
+
+        assert!(build_from_archive(&b""[..], 0, "de", dir, &Default::default()).is_err());
+        progress.cancel();
+        let canceled = build_from_archive(&b""[..], 0, "de", dir, &progress).unwrap_err();
+        assert_eq!(canceled.to_string(), CANCELED);
+    }
+}
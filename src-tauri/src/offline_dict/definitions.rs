@@ -0,0 +1,122 @@
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+use serde::Serialize;
+use serde_json::json;
+use std::error::Error;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+/// A dictzip-compressed definitions file is decompressed into this file of the dictionary directory,
+/// it is removed once the dictionary is built.
+pub(super) static DECOMPRESSED_FILENAME: &str = "definitions.tmp";
+
+/// The headword, offset and length of a definition in a definitions file.
+pub(super) type Record<W> = (W, usize, usize);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum DefinitionFormat {
+    Html,
+    Text,
+}
+
+/// An entry holding a definition of a StarDict or dictd dictionary as it is, plus its lines as
+/// plain-text glosses so that it is indexed and shown like a Wiktextract entry.
+pub(super) fn definition_entry(
+    definition: &str,
+    format: DefinitionFormat,
+) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let text = match format {
+        DefinitionFormat::Html => html_to_text(definition),
+        DefinitionFormat::Text => definition.to_owned(),
+    };
+    let glosses = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<&str>>();
+    let entry = json!({
+        "senses": [{ "glosses": glosses }],
+        "definition": { "format": format, "text": definition },
+    });
+    Ok(serde_json::to_vec(&entry)?)
+}
+
+/// Drops the tags of `html`, line breaks and block elements becoming new lines, and decodes the common entities.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut tag = None::<String>;
+    for c in html.chars() {
+        match (&mut tag, c) {
+            (None, '<') => tag = Some(String::new()),
+            (None, c) => text.push(c),
+            (Some(name), '>') => {
+                let name = name
+                    .trim_start_matches('/')
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_ascii_lowercase();
+                let is_block = matches!(
+                    name.as_str(),
+                    "br" | "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+                );
+                if is_block && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            (Some(name), c) => name.push(c),
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Maps the definitions of a StarDict or dictd dictionary, `{name}.dict` next to `path`, or its
+/// dictzip-compressed `{name}.dict.dz` once decompressed into `scratch`.
+pub(super) fn map_definitions(
+    path: &Path,
+    scratch: &Path,
+) -> Result<Mmap, Box<dyn Error + Send + Sync>> {
+    let plain = path.with_extension("dict");
+    let file = match File::open(&plain) {
+        Ok(file) => file,
+        Err(_) => {
+            let compressed = File::open(path.with_extension("dict.dz"))
+                .or(Err("definitions file (.dict or .dict.dz) not found"))?;
+            let mut decoder = GzDecoder::new(BufReader::new(compressed));
+            let mut decompressed = BufWriter::new(File::create(scratch)?);
+            io::copy(&mut decoder, &mut decompressed)?;
+            decompressed.flush()?;
+            File::open(scratch)?
+        }
+    };
+    // Safety: the file is only read while the dictionary is being built.
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{definition_entry, DefinitionFormat};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn html_definitions_get_plain_glosses() {
+        let html = "<b>house</b><br>a building &amp; home<ul><li>dwelling</li></ul>";
+        let entry = definition_entry(html, DefinitionFormat::Html).unwrap();
+        let entry = serde_json::from_slice::<Value>(&entry).unwrap();
+        assert_eq!(
+            entry["senses"][0]["glosses"],
+            json!(["house", "a building & home", "dwelling"])
+        );
+        assert_eq!(entry["definition"], json!({"format": "html", "text": html}));
+    }
+}
@@ -0,0 +1,138 @@
+use super::definitions::{
+    definition_entry, map_definitions, DefinitionFormat, Record, DECOMPRESSED_FILENAME,
+};
+use super::{BuildPhase, BuildProgress, DictIndexBuilder};
+use std::error::Error;
+use std::{fs, path::Path};
+
+/// Digits of the base-64 numbers of `.index` files, most significant first.
+static B64_DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// Headwords of the entries describing the database itself, such as `00-database-info`.
+static META_PREFIXES: &[&str] = &["00-database-", "00database"];
+/// Entries converted between two checks for cancellation.
+const CHECK_EVERY: usize = 1024;
+
+fn decode_number(digits: &str) -> Option<usize> {
+    digits.bytes().try_fold(0usize, |n, digit| {
+        let value = B64_DIGITS.iter().position(|d| *d == digit)?;
+        n.checked_mul(64)?.checked_add(value)
+    })
+}
+
+/// The `(headword, offset, length)` of every line of an `.index` file, the database entries left out.
+fn parse_index(index: &str) -> Result<Vec<Record<&str>>, Box<dyn Error + Send + Sync>> {
+    let mut records = Vec::new();
+    for line in index.lines().filter(|line| !line.trim().is_empty()) {
+        let mut columns = line.split('\t');
+        let (word, offset, length) = match (columns.next(), columns.next(), columns.next()) {
+            (Some(word), Some(offset), Some(length)) => (word, offset, length),
+            _ => Err(format!("malformed .index line: {line}"))?,
+        };
+        if META_PREFIXES.iter().any(|prefix| word.starts_with(prefix)) {
+            continue;
+        }
+        let offset = decode_number(offset).ok_or("malformed offset in .index file")?;
+        let length = decode_number(length).ok_or("malformed length in .index file")?;
+        records.push((word, offset, length));
+    }
+    Ok(records)
+}
+
+/// Builds a dictionary into `dir` from a dictd database, given the path of its `.index` file.
+/// The `.dict` file, dictzip-compressed or not, is expected next to it.
+pub fn build_from_dictd(
+    index_path: &Path,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let index = fs::read_to_string(index_path)?;
+    let records = parse_index(&index)?;
+    fs::create_dir_all(dir)?;
+    let scratch = Path::new(dir).join(DECOMPRESSED_FILENAME);
+    let definitions = map_definitions(index_path, &scratch)?;
+    let mut builder = DictIndexBuilder::new(dir)?.with_progress(progress.clone());
+
+    for (i, (word, offset, length)) in records.iter().enumerate() {
+        if i % CHECK_EVERY == 0 {
+            progress.check_canceled()?;
+            progress.report(BuildPhase::Converting, i as u64, records.len() as u64);
+        }
+        let data = offset
+            .checked_add(*length)
+            .and_then(|end| definitions.get(*offset..end))
+            .ok_or("definition out of the .dict file")?;
+        let definition = String::from_utf8_lossy(data);
+        if !definition.trim().is_empty() {
+            builder.push(
+                word,
+                &definition_entry(&definition, DefinitionFormat::Text)?,
+            )?;
+        }
+    }
+    drop(definitions);
+    if scratch.exists() {
+        fs::remove_file(&scratch)?;
+    }
+
+    if builder.is_empty() {
+        Err("no dictionary entries found")?
+    }
+    progress.enter(BuildPhase::Indexing);
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_from_dictd, decode_number};
+    use crate::offline_dict::DictIndex;
+    use crate::test_dir::TempDir;
+    use std::fs;
+
+    #[test]
+    fn dictd_dictionary_is_imported() {
+        assert_eq!(decode_number("A"), Some(0));
+        assert_eq!(decode_number("BA"), Some(64));
+        assert_eq!(decode_number("b/"), Some(27 * 64 + 63));
+        assert_eq!(decode_number("a-"), None);
+
+        let (src_dir, out_dir) = (TempDir::new("dictd_src"), TempDir::new("dictd"));
+        let (src, dir) = (src_dir.path(), out_dir.path());
+        let dict = "00-database-info\nA test database\nHaus\n  house, home\nsein\n  to be\n";
+        let index = "00-database-info\tA\th\nHaus\th\tT\nsein\t0\tN\n";
+        fs::write(src.join("de.dict"), dict).unwrap();
+        fs::write(src.join("de.index"), index).unwrap();
+
+        let dir_str = dir.to_str().unwrap();
+        build_from_dictd(&src.join("de.index"), dir_str, &Default::default()).unwrap();
+        let index = DictIndex::open(dir_str).unwrap();
+        let entry = index.get("Haus").unwrap().unwrap();
+        let definition = entry.as_array().unwrap()[0].get("definition").unwrap();
+        let text = definition.get("text").unwrap().as_string().unwrap();
+        assert_eq!(text.as_str(), "Haus\n  house, home\n");
+        assert!(index.get("sein").unwrap().is_some());
+        assert!(index.get("00-database-info").unwrap().is_none());
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn definitions_past_the_dict_file_are_rejected() {
+        let (src_dir, out_dir) = (
+            TempDir::new("dictd_overflow_src"),
+            TempDir::new("dictd_overflow"),
+        );
+        let (src, dir) = (src_dir.path(), out_dir.path());
+        fs::write(src.join("de.dict"), "Haus\n  house\n").unwrap();
+        // the offset is usize::MAX.
+        fs::write(src.join("de.index"), "Haus\tP//////////\tB\n").unwrap();
+
+        let built = build_from_dictd(
+            &src.join("de.index"),
+            dir.to_str().unwrap(),
+            &Default::default(),
+        );
+        assert_eq!(
+            built.unwrap_err().to_string(),
+            "definition out of the .dict file"
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use serde::Deserialize;
+
+/// The parts of a Wiktextract entry the index is built from. The entry itself is stored untouched.
+#[derive(Deserialize, Default)]
+pub(super) struct IndexedEntry {
+    #[serde(default)]
+    pub senses: Vec<IndexedSense>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct IndexedSense {
+    #[serde(default)]
+    pub glosses: Vec<String>,
+    #[serde(default)]
+    pub form_of: Vec<Link>,
+    #[serde(default)]
+    pub alt_of: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Link {
+    pub word: String,
+}
+
+impl IndexedSense {
+    pub fn links(&self) -> impl Iterator<Item = &Link> {
+        self.form_of.iter().chain(self.alt_of.iter())
+    }
+}
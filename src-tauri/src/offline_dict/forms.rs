@@ -0,0 +1,83 @@
+use super::{entry::IndexedEntry, DictIndex};
+use fst::{
+    automaton::{Automaton, Str},
+    IntoStreamer, Streamer,
+};
+
+/// Separates the form from its lemma in the keys of `forms.fst`, e.g. "ran\0run".
+const SEPARATOR: char = '\0';
+
+/// Keys of `forms.fst` contributed by one entry of `word`, one per `form_of`/`alt_of` link of its senses.
+pub(super) fn form_keys(word: &str, entry: &IndexedEntry) -> Vec<String> {
+    entry
+        .senses
+        .iter()
+        .flat_map(|sense| sense.links())
+        .filter(|link| link.word != word && !link.word.contains(SEPARATOR))
+        .map(|link| format!("{word}{SEPARATOR}{}", link.word))
+        .collect()
+}
+
+impl DictIndex {
+    /// Lemmas `form` is an inflection or alternative spelling of, e.g. "ran" gives ["run"].
+    pub fn lemmas_of(&self, form: &str) -> Vec<String> {
+        let prefix = format!("{form}{SEPARATOR}");
+        let mut stream = self
+            .forms
+            .search(Str::new(&prefix).starts_with())
+            .into_stream();
+        let mut lemmas = Vec::new();
+        while let Some(key) = stream.next() {
+            if let Ok(key) = std::str::from_utf8(key) {
+                lemmas.push(key[prefix.len()..].to_owned());
+            }
+        }
+        lemmas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::offline_dict::{DictIndex, DictIndexBuilder};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn forms_resolve_to_lemmas() {
+        let tmp = TempDir::new("forms");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut builder = DictIndexBuilder::new(dir).unwrap();
+        builder
+            .push(
+                "run",
+                br#"{"pos": "verb", "senses": [{"glosses": ["to move quickly"]}]}"#,
+            )
+            .unwrap();
+        builder
+            .push(
+                "ran",
+                br#"{"pos": "verb", "senses": [{"form_of": [{"word": "run"}]}]}"#,
+            )
+            .unwrap();
+        builder
+            .push(
+                "runned",
+                br#"{"senses": [{"alt_of": [{"word": "ran"}, {"word": "run"}]}]}"#,
+            )
+            .unwrap();
+        builder.finish().unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        assert_eq!(index.lemmas_of("ran"), vec!["run"]);
+        assert_eq!(index.lemmas_of("runned"), vec!["ran", "run"]);
+        assert!(index.lemmas_of("run").is_empty());
+
+        let lookup = index.lookup("Ran").unwrap().unwrap();
+        assert_eq!(lookup.word, "ran");
+        assert_eq!(lookup.lemmas[0].lemma, "run");
+        assert_eq!(
+            lookup.lemmas[0].entries,
+            ijson::ijson!([{"pos": "verb", "senses": [{"glosses": ["to move quickly"]}]}])
+        );
+    }
+}
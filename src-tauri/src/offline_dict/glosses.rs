@@ -0,0 +1,394 @@
+use super::{entry::IndexedEntry, BuildProgress, DictIndex};
+use fst::MapBuilder;
+use ijson::IValue;
+use serde::Serialize;
+use std::error::Error;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+pub(super) static GLOSSES_FILENAME: &str = "glosses.fst";
+pub(super) static POSTINGS_FILENAME: &str = "postings.dat";
+
+/// Weight of a gloss made of this single term, longer glosses weigh proportionally less.
+const FULL_WEIGHT: u16 = 1000;
+const MAX_MATCHED_GLOSSES: usize = 3;
+/// Postings collected before they are sorted and written out as a run, about 12 MB.
+const RUN_POSTINGS: usize = 1 << 20;
+/// Bytes of a posting in a run: u32 LE term id, u32 LE entry ordinal and u16 LE weight.
+const POSTING_LEN: usize = 10;
+static RUN_PREFIX: &str = "postings-run-";
+
+/// `(term id, entry ordinal, weight)`
+type Posting = (u32, u32, u16);
+
+static STOPWORDS: &[&str] = &[
+    "a", "an", "and", "any", "are", "as", "at", "be", "by", "for", "from", "in", "into", "is",
+    "it", "its", "of", "on", "or", "that", "the", "this", "to", "which", "with",
+];
+
+#[derive(Serialize, Debug)]
+pub struct ReverseMatch {
+    pub word: String,
+    pub score: f32,
+    /// Glosses of `word` containing a term of the query.
+    pub glosses: Vec<String>,
+}
+
+/// Lowercased terms of an English text, stopwords left out.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.to_lowercase())
+        .filter(|term| !STOPWORDS.contains(&term.as_str()))
+}
+
+/// Collects the gloss terms of every pushed entry and writes `glosses.fst`, mapping each term
+/// to its postings in `postings.dat`.
+///
+/// A postings record is a u32 LE count followed by `(u16 weight, u16 length, headword)` triples,
+/// heaviest first. The weight of a headword for a term is taken from its shortest gloss containing
+/// the term, so "butterfly" weighs the most for the headword glossed exactly as "butterfly".
+///
+/// Postings are sorted in runs of `run_len` and written to temporary files, which `finish` merges,
+/// so only the terms stay in memory however large the dictionary is.
+pub(super) struct GlossIndexBuilder {
+    dir: PathBuf,
+    term_ids: HashMap<String, u32>,
+    /// Postings of the run being collected.
+    postings: Vec<Posting>,
+    run_len: usize,
+    runs: Vec<PathBuf>,
+}
+
+impl GlossIndexBuilder {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            term_ids: HashMap::new(),
+            postings: Vec::new(),
+            run_len: RUN_POSTINGS,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        ordinal: u32,
+        entry: &IndexedEntry,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // glosses of inflected forms ("simple past of run") describe grammar, not meaning.
+        for sense in entry.senses.iter().filter(|s| s.links().next().is_none()) {
+            for gloss in &sense.glosses {
+                let gloss_terms = terms(gloss).collect::<Vec<String>>();
+                let weight = FULL_WEIGHT / gloss_terms.len().clamp(1, FULL_WEIGHT as usize) as u16;
+                for term in gloss_terms {
+                    let next_id = self.term_ids.len() as u32;
+                    let term_id = *self.term_ids.entry(term).or_insert(next_id);
+                    self.postings.push((term_id, ordinal, weight));
+                }
+            }
+        }
+        if self.postings.len() >= self.run_len {
+            self.write_run()?;
+        }
+        Ok(())
+    }
+
+    fn write_run(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.postings.sort_unstable();
+        let path = self
+            .dir
+            .join(format!("{RUN_PREFIX}{}.tmp", self.runs.len()));
+        let mut run = BufWriter::new(File::create(&path)?);
+        for (term_id, ordinal, weight) in self.postings.drain(..) {
+            run.write_all(&term_id.to_le_bytes())?;
+            run.write_all(&ordinal.to_le_bytes())?;
+            run.write_all(&weight.to_le_bytes())?;
+        }
+        run.flush()?;
+        self.runs.push(path);
+        Ok(())
+    }
+
+    /// `headword` resolves the ordinal of an entry, as passed to `add`, to its headword.
+    pub fn finish<'a>(
+        &mut self,
+        headword: impl Fn(u32) -> &'a str,
+        progress: &BuildProgress,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.write_run()?;
+        let mut runs = self
+            .runs
+            .iter()
+            .map(|path| Ok(BufReader::new(File::open(path)?)))
+            .collect::<io::Result<Vec<BufReader<File>>>>()?;
+        // the smallest `(term id, ordinal)` at the head of every run.
+        let mut heads = BinaryHeap::new();
+        for (run, reader) in runs.iter_mut().enumerate() {
+            if let Some((term_id, ordinal, weight)) = read_posting(reader)? {
+                heads.push(Reverse((term_id, ordinal, weight, run)));
+            }
+        }
+
+        let mut postings = BufWriter::new(File::create(self.dir.join(POSTINGS_FILENAME))?);
+        let mut offsets = vec![0u64; self.term_ids.len()];
+        let mut offset = 0u64;
+        let mut weights = HashMap::<&str, u16>::new();
+        while let Some(Reverse((term_id, ordinal, weight, run))) = heads.pop() {
+            if let Some((term_id, ordinal, weight)) = read_posting(&mut runs[run])? {
+                heads.push(Reverse((term_id, ordinal, weight, run)));
+            }
+            let best = weights.entry(headword(ordinal)).or_insert(0);
+            *best = (*best).max(weight);
+            if matches!(heads.peek(), Some(Reverse((next, ..))) if *next == term_id) {
+                continue;
+            }
+            progress.check_canceled()?;
+
+            // every posting of the term has been merged.
+            let mut sorted = weights.drain().collect::<Vec<(&str, u16)>>();
+            sorted.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            offsets[term_id as usize] = offset;
+            postings.write_all(&(sorted.len() as u32).to_le_bytes())?;
+            offset += 4;
+            for (word, weight) in sorted {
+                let word = truncate(word, u16::MAX as usize).as_bytes();
+                postings.write_all(&weight.to_le_bytes())?;
+                postings.write_all(&(word.len() as u16).to_le_bytes())?;
+                postings.write_all(word)?;
+                offset += 4 + word.len() as u64;
+            }
+        }
+        postings.flush()?;
+        drop(runs);
+        for path in self.runs.drain(..) {
+            fs::remove_file(path)?;
+        }
+
+        let mut sorted_terms = std::mem::take(&mut self.term_ids)
+            .into_iter()
+            .collect::<Vec<(String, u32)>>();
+        sorted_terms.sort();
+        let mut glosses = MapBuilder::new(BufWriter::new(File::create(
+            self.dir.join(GLOSSES_FILENAME),
+        )?))?;
+        for (term, term_id) in sorted_terms {
+            glosses.insert(term, offsets[term_id as usize])?;
+        }
+        glosses.finish()?;
+        Ok(())
+    }
+}
+
+/// The next posting of a run, `None` at its end.
+fn read_posting(run: &mut impl Read) -> Result<Option<Posting>, Box<dyn Error + Send + Sync>> {
+    let mut record = [0; POSTING_LEN];
+    match run.read_exact(&mut record) {
+        Ok(()) => Ok(Some((
+            u32::from_le_bytes(record[0..4].try_into()?),
+            u32::from_le_bytes(record[4..8].try_into()?),
+            u16::from_le_bytes(record[8..10].try_into()?),
+        ))),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl DictIndex {
+    /// Headwords whose English glosses contain the terms of `query`, most relevant first.
+    ///
+    /// Each term scores its weight times its inverse document frequency, so rare terms and
+    /// tight glosses count the most.
+    pub fn reverse_search(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ReverseMatch>, Box<dyn Error + Send + Sync>> {
+        let query_terms = terms(query).collect::<Vec<String>>();
+        let total = self.words.len().max(1) as f32;
+
+        let mut scores = HashMap::<String, f32>::new();
+        for term in &query_terms {
+            let offset = match self.glosses.get(term) {
+                Some(offset) => offset,
+                None => continue,
+            };
+            let postings = self.read_postings(offset)?;
+            let idf = (1.0 + total / postings.len() as f32).ln();
+            for (word, weight) in postings {
+                *scores.entry(word).or_insert(0.0) += idf * weight as f32 / FULL_WEIGHT as f32;
+            }
+        }
+
+        let mut ranked = scores.into_iter().collect::<Vec<(String, f32)>>();
+        ranked.sort_by(|a, b| {
+            b.1.total_cmp(&a.1)
+                .then(a.0.chars().count().cmp(&b.0.chars().count()))
+                .then(a.0.cmp(&b.0))
+        });
+        ranked.truncate(limit);
+
+        let mut matches = Vec::with_capacity(ranked.len());
+        for (word, score) in ranked {
+            let glosses = match self.get(&word)? {
+                Some(entries) => matched_glosses(&entries, &query_terms),
+                None => vec![],
+            };
+            matches.push(ReverseMatch {
+                word,
+                score,
+                glosses,
+            });
+        }
+        Ok(matches)
+    }
+
+    fn read_postings(
+        &self,
+        offset: u64,
+    ) -> Result<Vec<(String, u16)>, Box<dyn Error + Send + Sync>> {
+        let bytes = &self.postings[..];
+        let read_u16 = |at: usize| -> Result<u16, Box<dyn Error + Send + Sync>> {
+            let b = bytes.get(at..at + 2).ok_or("postings out of bounds")?;
+            Ok(u16::from_le_bytes(b.try_into()?))
+        };
+
+        let mut at = offset as usize;
+        let count = bytes.get(at..at + 4).ok_or("postings out of bounds")?;
+        let count = u32::from_le_bytes(count.try_into()?);
+        at += 4;
+        let mut postings = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let weight = read_u16(at)?;
+            let len = read_u16(at + 2)? as usize;
+            let word = bytes
+                .get(at + 4..at + 4 + len)
+                .ok_or("postings out of bounds")?;
+            postings.push((String::from_utf8_lossy(word).into_owned(), weight));
+            at += 4 + len;
+        }
+        Ok(postings)
+    }
+}
+
+fn matched_glosses(entries: &IValue, query_terms: &[String]) -> Vec<String> {
+    let glosses = entries
+        .as_array()
+        .into_iter()
+        .flat_map(|entries| entries.iter())
+        .filter_map(|entry| entry.get("senses")?.as_array())
+        .flat_map(|senses| senses.iter())
+        .filter_map(|sense| sense.get("glosses")?.as_array())
+        .flat_map(|glosses| glosses.iter())
+        .filter_map(|gloss| Some(gloss.as_string()?.as_str()));
+
+    glosses
+        .filter(|gloss| terms(gloss).any(|term| query_terms.contains(&term)))
+        .take(MAX_MATCHED_GLOSSES)
+        .map(|gloss| gloss.to_owned())
+        .collect()
+}
+
+/// The longest prefix of `s` of at most `max_len` bytes that does not split a character.
+fn truncate(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{truncate, GlossIndexBuilder, GLOSSES_FILENAME, POSTINGS_FILENAME};
+    use crate::offline_dict::{entry::IndexedEntry, DictIndex, DictIndexBuilder};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn reverse_search_ranks_tight_glosses_first() {
+        let tmp = TempDir::new("glosses");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut builder = DictIndexBuilder::new(dir).unwrap();
+        builder
+            .push(
+                "Schmetterling",
+                br#"{"senses": [{"glosses": ["butterfly"]}]}"#,
+            )
+            .unwrap();
+        builder
+            .push(
+                "Schmetterlingsstil",
+                br#"{"senses": [{"glosses": ["butterfly stroke (swimming style)"]}]}"#,
+            )
+            .unwrap();
+        builder
+            .push(
+                "Falter",
+                br#"{"senses": [{"glosses": ["moth"]}, {"glosses": ["butterfly, moth"]}]}"#,
+            )
+            .unwrap();
+        builder
+            .push("Schmetterlinge", br#"{"senses": [{"glosses": ["plural of Schmetterling"], "form_of": [{"word": "Schmetterling"}]}]}"#)
+            .unwrap();
+        builder
+            .push("Hund", br#"{"senses": [{"glosses": ["dog"]}]}"#)
+            .unwrap();
+        builder.finish().unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        let matches = index.reverse_search("Butterfly", 10).unwrap();
+        let words = matches
+            .iter()
+            .map(|m| m.word.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(words, vec!["Schmetterling", "Falter", "Schmetterlingsstil"]);
+        assert_eq!(matches[1].glosses, vec!["butterfly, moth"]);
+        assert!(index.reverse_search("the", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn merged_runs_match_a_single_run() {
+        let glosses = [
+            r#"{"senses": [{"glosses": ["house"]}, {"glosses": ["home, dwelling"]}]}"#,
+            r#"{"senses": [{"glosses": ["dog"]}]}"#,
+            r#"{"senses": [{"glosses": ["small house, hut"]}]}"#,
+            r#"{"senses": [{"glosses": ["dog house"]}]}"#,
+        ];
+        let headwords = ["Haus", "Hund", "Hütte", "Hundehütte"];
+        let build = |run_len: usize| {
+            let tmp = TempDir::new("glosses_runs");
+            let dir = tmp.path();
+            let mut builder = GlossIndexBuilder::new(dir);
+            builder.run_len = run_len;
+            for (ordinal, entry) in glosses.iter().enumerate() {
+                let entry = serde_json::from_str::<IndexedEntry>(entry).unwrap();
+                builder.add(ordinal as u32, &entry).unwrap();
+            }
+            builder
+                .finish(|ordinal| headwords[ordinal as usize], &Default::default())
+                .unwrap();
+            let files = [GLOSSES_FILENAME, POSTINGS_FILENAME]
+                .map(|file| std::fs::read(dir.join(file)).unwrap());
+            assert_eq!(std::fs::read_dir(dir).unwrap().count(), 2);
+            files
+        };
+        assert_eq!(build(2), build(usize::MAX));
+    }
+
+    #[test]
+    fn long_words_are_truncated_between_characters() {
+        assert_eq!(truncate("Haus", 10), "Haus");
+        assert_eq!(truncate("Häuser", 2), "H");
+        assert_eq!(truncate("Häuser", 3), "Hä");
+        assert_eq!(truncate("日本", 2), "");
+    }
+}
@@ -0,0 +1,238 @@
+use super::{entry::IndexedEntry, forms, glosses, BuildPhase, BuildProgress};
+use fst::{Map, MapBuilder, Set, SetBuilder};
+use ijson::IValue;
+use memmap2::Mmap;
+use std::error::Error;
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+static WORDS_FILENAME: &str = "words.fst";
+static FORMS_FILENAME: &str = "forms.fst";
+static ENTRIES_FILENAME: &str = "entries.dat";
+static SPILL_FILENAME: &str = "entries.tmp";
+
+/// Written at the beginning of `entries.dat`, followed by `FORMAT_VERSION` (u32 LE).
+const ENTRIES_MAGIC: &[u8; 4] = b"TBDE";
+const FORMAT_VERSION: u32 = 4;
+const HEADER_LEN: u64 = 8;
+/// Headwords written between two checks for a cancellation.
+const CHECK_EVERY: usize = 1024;
+
+/// A read-only offline dictionary living on disk.
+///
+/// `words.fst` maps every headword to the byte offset of its record in `entries.dat`.
+/// A record is a u32 LE length followed by a JSON array holding all entries of the headword
+/// (every part of speech and etymology) in source order. `forms.fst` links inflected and
+/// alternative forms to their lemmas, see `DictIndex::lemmas_of`, and `glosses.fst` with `postings.dat`
+/// index the terms of the English glosses, see `DictIndex::reverse_search`. All files are memory-mapped,
+/// so opening a dictionary is instant and only the looked-up record is ever decoded.
+pub struct DictIndex {
+    pub(super) words: Map<Mmap>,
+    pub(super) forms: Set<Mmap>,
+    pub(super) glosses: Map<Mmap>,
+    pub(super) postings: Mmap,
+    entries: Mmap,
+}
+
+impl DictIndex {
+    pub fn open(dir: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let dir = Path::new(dir);
+        let words_file = File::open(dir.join(WORDS_FILENAME))?;
+        let forms_file = File::open(dir.join(FORMS_FILENAME))?;
+        let entries_file = File::open(dir.join(ENTRIES_FILENAME))?;
+        // Safety: dictionaries are built in a separate directory and swapped in, the files are never modified in place.
+        let words = Map::new(unsafe { Mmap::map(&words_file)? })?;
+        let forms = Set::new(unsafe { Mmap::map(&forms_file)? })?;
+        let glosses =
+            Map::new(unsafe { Mmap::map(&File::open(dir.join(glosses::GLOSSES_FILENAME))?)? })?;
+        let postings = unsafe { Mmap::map(&File::open(dir.join(glosses::POSTINGS_FILENAME))?)? };
+        let entries = unsafe { Mmap::map(&entries_file)? };
+
+        if entries.len() < HEADER_LEN as usize || &entries[..4] != ENTRIES_MAGIC {
+            Err("invalid dictionary file")?
+        }
+        let version = u32::from_le_bytes(entries[4..8].try_into()?);
+        if version != FORMAT_VERSION {
+            Err(format!(
+                "dictionary format v{version} is not supported, please download it again"
+            ))?
+        }
+
+        Ok(Self {
+            words,
+            forms,
+            glosses,
+            postings,
+            entries,
+        })
+    }
+
+    pub fn get(&self, word: &str) -> Result<Option<IValue>, Box<dyn Error + Send + Sync>> {
+        match self.words.get(word) {
+            Some(offset) => Ok(Some(self.read_record(offset)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn read_record(&self, offset: u64) -> Result<IValue, Box<dyn Error + Send + Sync>> {
+        let start = offset as usize;
+        let len_bytes = self
+            .entries
+            .get(start..start + 4)
+            .ok_or("record offset out of bounds")?;
+        let len = u32::from_le_bytes(len_bytes.try_into()?) as usize;
+        let record = self
+            .entries
+            .get(start + 4..start + 4 + len)
+            .ok_or("record length out of bounds")?;
+        Ok(serde_json::from_slice(record)?)
+    }
+}
+
+/// Collects `(headword, entry JSON)` pairs in any order and writes a `DictIndex` into `dir`.
+///
+/// Entries are spilled to a temporary file as they come, so only the headwords and
+/// their offsets are kept in memory until `finish` sorts them.
+pub struct DictIndexBuilder {
+    dir: PathBuf,
+    spill: BufWriter<File>,
+    spill_len: u64,
+    keys: Vec<(String, u64, u32)>,
+    forms: Vec<String>,
+    glosses: glosses::GlossIndexBuilder,
+    progress: BuildProgress,
+}
+
+impl DictIndexBuilder {
+    pub fn new(dir: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)?;
+        let spill = BufWriter::new(File::create(dir.join(SPILL_FILENAME))?);
+        Ok(Self {
+            glosses: glosses::GlossIndexBuilder::new(&dir),
+            dir,
+            spill,
+            spill_len: 0,
+            keys: Vec::new(),
+            forms: Vec::new(),
+            progress: Default::default(),
+        })
+    }
+
+    /// Reports the progress of `finish` as `BuildPhase::Indexing` and stops it once the build is canceled.
+    pub fn with_progress(mut self, progress: BuildProgress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn push(&mut self, word: &str, entry: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let indexed = serde_json::from_slice::<IndexedEntry>(entry).unwrap_or_default();
+        self.forms.extend(forms::form_keys(word, &indexed));
+        self.glosses.add(self.keys.len() as u32, &indexed)?;
+
+        self.spill.write_all(entry)?;
+        self.keys
+            .push((word.to_owned(), self.spill_len, entry.len() as u32));
+        self.spill_len += entry.len() as u64;
+        Ok(())
+    }
+
+    /// Sorts the headwords, copies their entries into `entries.dat` in that order and builds `words.fst`,
+    /// `forms.fst` and the gloss index. Entries sharing a headword are grouped into one record.
+    pub fn finish(mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.spill.flush()?;
+        let spill_path = self.dir.join(SPILL_FILENAME);
+        let spill = unsafe { Mmap::map(&File::open(&spill_path)?)? };
+
+        // ordinals refer to the order of `push`, so this must run before the headwords are sorted.
+        let keys = &self.keys;
+        self.glosses
+            .finish(|ordinal| keys[ordinal as usize].0.as_str(), &self.progress)?;
+
+        // stable sort keeps the input order among the entries of a headword.
+        self.keys.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut entries = BufWriter::new(File::create(self.dir.join(ENTRIES_FILENAME))?);
+        entries.write_all(ENTRIES_MAGIC)?;
+        entries.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        let mut words =
+            MapBuilder::new(BufWriter::new(File::create(self.dir.join(WORDS_FILENAME))?))?;
+
+        let mut offset = HEADER_LEN;
+        let mut rest = &self.keys[..];
+        let mut written = 0;
+        while let Some((word, _, _)) = rest.first() {
+            let group_len = rest.iter().take_while(|(w, _, _)| w == word).count();
+            let (group, next) = rest.split_at(group_len);
+            rest = next;
+            written += group_len;
+            if written % CHECK_EVERY < group_len {
+                self.progress.check_canceled()?;
+                self.progress
+                    .report(BuildPhase::Indexing, written as u64, self.keys.len() as u64);
+            }
+            // "[" + entries separated by "," + "]"
+            let len = group.iter().map(|(_, _, len)| *len + 1).sum::<u32>() + 1;
+            entries.write_all(&len.to_le_bytes())?;
+            for (i, (_, spill_offset, entry_len)) in group.iter().enumerate() {
+                let start = *spill_offset as usize;
+                entries.write_all(if i == 0 { b"[" } else { b"," })?;
+                entries.write_all(&spill[start..start + *entry_len as usize])?;
+            }
+            entries.write_all(b"]")?;
+            words.insert(word, offset)?;
+            offset += 4 + len as u64;
+        }
+        entries.flush()?;
+        words.finish()?;
+        self.progress
+            .report(BuildPhase::Indexing, written as u64, self.keys.len() as u64);
+
+        self.forms.sort();
+        self.forms.dedup();
+        let mut forms =
+            SetBuilder::new(BufWriter::new(File::create(self.dir.join(FORMS_FILENAME))?))?;
+        forms.extend_iter(&self.forms)?;
+        forms.finish()?;
+
+        drop(spill);
+        fs::remove_file(spill_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DictIndex, DictIndexBuilder};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn build_and_lookup_works() {
+        let tmp = TempDir::new("index");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut builder = DictIndexBuilder::new(dir).unwrap();
+        builder.push("run", br#"{"pos": "noun"}"#).unwrap();
+        builder.push("apple", br#"{"pos": "noun"}"#).unwrap();
+        builder.push("run", br#"{"pos": "verb"}"#).unwrap();
+        builder.finish().unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        assert_eq!(
+            index.get("run").unwrap(),
+            Some(ijson::ijson!([{"pos": "noun"}, {"pos": "verb"}]))
+        );
+        assert_eq!(
+            index.get("apple").unwrap(),
+            Some(ijson::ijson!([{"pos": "noun"}]))
+        );
+        assert_eq!(index.get("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,29 @@
+mod archive;
+mod catalog;
+mod convert;
+mod definitions;
+mod dictd;
+mod entry;
+mod forms;
+mod glosses;
+mod index;
+mod progress;
+mod registry;
+mod search;
+mod stardict;
+
+pub use self::archive::verify_archive;
+pub use self::catalog::{
+    available_dicts, find_catalog_dict, refresh_catalog, AvailableDict, CatalogDict,
+    DICT_META_FILENAME,
+};
+pub use self::convert::{
+    build_from_archive, build_from_jsonl, build_from_legacy_json, ChunkReader,
+};
+pub use self::dictd::build_from_dictd;
+pub use self::glosses::ReverseMatch;
+pub use self::index::{DictIndex, DictIndexBuilder};
+pub use self::progress::{BuildPhase, BuildProgress, ProgressReader, CANCELED};
+pub use self::registry::{is_valid_abbr, DictRegistry};
+pub use self::search::OfflineLookup;
+pub use self::stardict::build_from_stardict;
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// How often a phase reports its percentage at most, the end of a phase is always reported.
+const REPORT_INTERVAL: Duration = Duration::from_millis(500);
+pub static CANCELED: &str = "canceled";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildPhase {
+    Downloading,
+    Extracting,
+    Converting,
+    Indexing,
+}
+
+struct Reported {
+    phase: BuildPhase,
+    percentage: u8,
+    at: Instant,
+}
+
+/// Progress and cancellation of a dictionary build, shared by every thread taking part in it.
+///
+/// The stages of the pipeline run concurrently, so only the phase last entered with `enter` is reported.
+/// The percentages of the stages catching up behind it are left out instead of flickering in between.
+#[derive(Clone)]
+pub struct BuildProgress {
+    emit: Arc<dyn Fn(BuildPhase, u8) + Send + Sync>,
+    canceled: Arc<AtomicBool>,
+    reported: Arc<Mutex<Reported>>,
+}
+
+impl BuildProgress {
+    pub fn new(
+        first_phase: BuildPhase,
+        emit: impl Fn(BuildPhase, u8) + Send + Sync + 'static,
+    ) -> Self {
+        emit(first_phase, 0);
+        Self {
+            emit: Arc::new(emit),
+            canceled: Arc::new(AtomicBool::new(false)),
+            reported: Arc::new(Mutex::new(Reported {
+                phase: first_phase,
+                percentage: 0,
+                at: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn enter(&self, phase: BuildPhase) {
+        *self.reported.lock().unwrap() = Reported {
+            phase,
+            percentage: 0,
+            at: Instant::now(),
+        };
+        (self.emit)(phase, 0);
+    }
+
+    pub fn report(&self, phase: BuildPhase, done: u64, total: u64) {
+        let percentage = (done.saturating_mul(100) / total.max(1)).min(100) as u8;
+        {
+            let mut reported = self.reported.lock().unwrap();
+            if reported.phase != phase
+                || reported.percentage == percentage
+                || (percentage < 100 && reported.at.elapsed() < REPORT_INTERVAL)
+            {
+                return;
+            }
+            reported.percentage = percentage;
+            reported.at = Instant::now();
+        }
+        (self.emit)(phase, percentage);
+    }
+
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::Relaxed)
+    }
+
+    pub fn check_canceled(&self) -> Result<(), &'static str> {
+        match self.is_canceled() {
+            true => Err(CANCELED),
+            false => Ok(()),
+        }
+    }
+}
+
+impl Default for BuildProgress {
+    /// Reports nowhere, for builds nobody is watching.
+    fn default() -> Self {
+        Self::new(BuildPhase::Converting, |_, _| {})
+    }
+}
+
+/// Reports the bytes read through it as the progress of `phase`, and fails once the build is canceled.
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: BuildProgress,
+    phase: BuildPhase,
+    read: u64,
+    total: u64,
+}
+
+impl<R: Read> ProgressReader<R> {
+    pub fn new(inner: R, progress: BuildProgress, phase: BuildPhase, total: u64) -> Self {
+        Self {
+            inner,
+            progress,
+            phase,
+            read: 0,
+            total,
+        }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.progress
+            .check_canceled()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        self.progress.report(self.phase, self.read, self.total);
+        Ok(n)
+    }
+}
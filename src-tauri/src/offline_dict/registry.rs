@@ -0,0 +1,74 @@
+use super::DictIndex;
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{Arc, RwLock},
+};
+
+/// Offline dictionaries opened so far, keyed by language code.
+///
+/// A dictionary is opened on its first lookup. Failures are not cached, so a dictionary
+/// looked up before it was downloaded becomes available as soon as it is installed.
+pub struct DictRegistry {
+    base_dir: String,
+    loaded: RwLock<HashMap<String, Arc<DictIndex>>>,
+}
+
+/// Language codes double as directory names, so only plain codes like "en" or "zh-CN" are accepted.
+pub fn is_valid_abbr(abbr: &str) -> bool {
+    !abbr.is_empty()
+        && abbr.len() <= 16
+        && abbr
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl DictRegistry {
+    pub fn new(base_dir: String) -> Self {
+        Self {
+            base_dir,
+            loaded: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, abbr: &str) -> Result<Arc<DictIndex>, String> {
+        if let Some(dict) = self.loaded.read().unwrap().get(abbr) {
+            return Ok(Arc::clone(dict));
+        }
+        let dict = Arc::new(self.open(abbr)?);
+        let mut loaded = self.loaded.write().unwrap();
+        // another lookup may have opened it in the meantime, keep the first one.
+        Ok(Arc::clone(loaded.entry(abbr.to_owned()).or_insert(dict)))
+    }
+
+    /// Opens the dictionary again, e.g. after `download_dict` rebuilt it.
+    pub fn reload(&self, abbr: &str) -> Result<(), String> {
+        let dict = Arc::new(self.open(abbr)?);
+        self.loaded.write().unwrap().insert(abbr.to_owned(), dict);
+        Ok(())
+    }
+
+    /// Forgets the dictionary. Its memory maps are released once in-flight lookups are done.
+    pub fn unload(&self, abbr: &str) {
+        self.loaded.write().unwrap().remove(abbr);
+    }
+
+    fn open(&self, abbr: &str) -> Result<DictIndex, String> {
+        if !is_valid_abbr(abbr) {
+            return Err("language not found".to_string());
+        }
+        let dir = format!("{}/{abbr}", self.base_dir);
+        if fs::metadata(&dir).is_err() {
+            // dictionaries downloaded as a single json file by older versions are converted at startup.
+            if fs::metadata(format!("{dir}.json")).is_ok() {
+                return Err(format!(
+                    "the {abbr} dictionary is being converted, please try again in a moment"
+                ));
+            }
+            return Err(format!(
+                "the {abbr} dictionary is not installed, please download it again"
+            ));
+        }
+        DictIndex::open(&dir).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,262 @@
+use super::DictIndex;
+use fst::{
+    automaton::{Automaton, Levenshtein, Str},
+    IntoStreamer, Streamer,
+};
+use ijson::{IArray, IValue};
+use serde::Serialize;
+use std::collections::{BinaryHeap, HashSet};
+use std::error::Error;
+
+/// Words shorter than this are not fuzzy-matched, almost any headword is one typo away from them.
+const MIN_FUZZY_LEN: usize = 3;
+const MAX_FUZZY_MATCHES: usize = 10;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    pub word: String,
+    pub distance: u32,
+}
+
+#[derive(Serialize)]
+pub struct LemmaEntries {
+    pub lemma: String,
+    pub entries: IValue,
+}
+
+#[derive(Serialize)]
+pub struct OfflineLookup {
+    /// The headword or form that was matched.
+    pub word: String,
+    /// Entries of `word` itself, empty when it is only known as a form of a lemma.
+    pub entries: IValue,
+    /// Set when `word` is an inflected or alternative form, e.g. "ran" of "run".
+    pub lemmas: Vec<LemmaEntries>,
+    /// Closest headwords when `word` is not in the dictionary at all.
+    pub did_you_mean: Vec<FuzzyMatch>,
+}
+
+impl OfflineLookup {
+    /// Whether `word` itself or a lemma of it is in the dictionary, rather than only close headwords.
+    pub fn found(&self) -> bool {
+        self.did_you_mean.is_empty()
+    }
+}
+
+impl DictIndex {
+    /// Looks `word` up lowercased, then as typed since headwords like German nouns only exist capitalized.
+    /// Inflected forms come with the entries of their lemmas. On a miss, falls back to the closest
+    /// headwords by edit distance.
+    pub fn lookup(
+        &self,
+        word: &str,
+    ) -> Result<Option<OfflineLookup>, Box<dyn Error + Send + Sync>> {
+        let word = word.trim();
+        let lowercase = word.to_lowercase();
+
+        for candidate in [lowercase.as_str(), word] {
+            let entries = self.get(candidate)?;
+            let lemmas = self.lemma_entries(candidate)?;
+            if entries.is_none() && lemmas.is_empty() {
+                continue;
+            }
+            return Ok(Some(OfflineLookup {
+                word: candidate.to_owned(),
+                entries: entries.unwrap_or_else(|| IArray::new().into()),
+                lemmas,
+                did_you_mean: vec![],
+            }));
+        }
+
+        let did_you_mean = self.fuzzy(word, MAX_FUZZY_MATCHES);
+        if did_you_mean.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(OfflineLookup {
+            word: word.to_owned(),
+            entries: IArray::new().into(),
+            lemmas: vec![],
+            did_you_mean,
+        }))
+    }
+
+    fn lemma_entries(&self, form: &str) -> Result<Vec<LemmaEntries>, Box<dyn Error + Send + Sync>> {
+        let mut lemmas = Vec::new();
+        for lemma in self.lemmas_of(form) {
+            if let Some(entries) = self.get(&lemma)? {
+                lemmas.push(LemmaEntries { lemma, entries });
+            }
+        }
+        Ok(lemmas)
+    }
+
+    /// Headwords starting with `prefix`, shortest first.
+    ///
+    /// The prefix is matched both lowercased and capitalized, so typing "haus" also suggests
+    /// German nouns like "Haus". Ties in length are broken by preferring the lowercase spelling,
+    /// then alphabetically.
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let lowercase = prefix.trim().to_lowercase();
+        if lowercase.is_empty() || limit == 0 {
+            return vec![];
+        }
+        // max-heap of the best `limit` candidates, the worst one sits on top to be evicted.
+        let mut best = BinaryHeap::with_capacity(limit + 1);
+        for (variant_rank, variant) in case_variants(&lowercase).iter().enumerate() {
+            let mut stream = self
+                .words
+                .search(Str::new(variant).starts_with())
+                .into_stream();
+            while let Some((key, _)) = stream.next() {
+                let word = match std::str::from_utf8(key) {
+                    Ok(word) => word,
+                    Err(_) => continue,
+                };
+                let rank = (word.chars().count(), variant_rank);
+                if best.len() == limit {
+                    let (worst_rank, worst_word): &((usize, usize), String) = best.peek().unwrap();
+                    if (rank, word) >= (*worst_rank, worst_word.as_str()) {
+                        continue;
+                    }
+                    best.pop();
+                }
+                best.push((rank, word.to_owned()));
+            }
+        }
+
+        best.into_sorted_vec()
+            .into_iter()
+            .map(|(_, word)| word)
+            .collect()
+    }
+
+    /// Headwords within a small edit distance of `word`, closest first.
+    ///
+    /// The allowed distance grows with the word: one edit up to 4 characters, two edits beyond.
+    /// Case is ignored when measuring the distance.
+    pub fn fuzzy(&self, word: &str, limit: usize) -> Vec<FuzzyMatch> {
+        let lowercase = word.trim().to_lowercase();
+        let len = lowercase.chars().count();
+        if len < MIN_FUZZY_LEN || limit == 0 {
+            return vec![];
+        }
+        let max_distance = if len <= 4 { 1 } else { 2 };
+
+        let mut seen = HashSet::new();
+        let mut matches = Vec::new();
+        for variant in case_variants(&lowercase) {
+            // construction fails when the automaton would get too large, there is nothing to match then.
+            let automaton = match Levenshtein::new(&variant, max_distance) {
+                Ok(automaton) => automaton,
+                Err(_) => continue,
+            };
+            let mut stream = self.words.search(automaton).into_stream();
+            while let Some((key, _)) = stream.next() {
+                let candidate = match std::str::from_utf8(key) {
+                    Ok(candidate) => candidate,
+                    Err(_) => continue,
+                };
+                if !seen.insert(candidate.to_owned()) {
+                    continue;
+                }
+                matches.push(FuzzyMatch {
+                    word: candidate.to_owned(),
+                    distance: levenshtein(&lowercase, &candidate.to_lowercase()),
+                });
+            }
+        }
+
+        matches.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| len_diff(&a.word, len).cmp(&len_diff(&b.word, len)))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        matches.truncate(limit);
+        matches
+    }
+}
+
+/// The lowercase spelling and, when different, the capitalized one.
+fn case_variants(lowercase: &str) -> Vec<String> {
+    let mut variants = vec![lowercase.to_owned()];
+    let capitalized = capitalize(lowercase);
+    if capitalized != lowercase {
+        variants.push(capitalized);
+    }
+    variants
+}
+
+fn len_diff(word: &str, len: usize) -> usize {
+    word.chars().count().abs_diff(len)
+}
+
+fn levenshtein(a: &str, b: &str) -> u32 {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut prev = (0..=b.len() as u32).collect::<Vec<u32>>();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i as u32 + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::offline_dict::{DictIndex, DictIndexBuilder};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn suggest_ranks_shorter_and_lowercase_first() {
+        let tmp = TempDir::new("suggest");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut builder = DictIndexBuilder::new(dir).unwrap();
+        for word in ["hausen", "Haus", "haus", "Hausaufgabe", "hat", "house"] {
+            builder.push(word, b"{}").unwrap();
+        }
+        builder.finish().unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        assert_eq!(index.suggest("Hau", 3), vec!["haus", "Haus", "hausen"]);
+        assert_eq!(index.suggest("ho", 10), vec!["house"]);
+        assert!(index.suggest("x", 10).is_empty());
+    }
+
+    #[test]
+    fn fuzzy_finds_close_headwords() {
+        let tmp = TempDir::new("fuzzy");
+        let dir = tmp.path();
+        let dir = dir.to_str().unwrap();
+        let mut builder = DictIndexBuilder::new(dir).unwrap();
+        for word in ["receive", "recipe", "Haus", "house"] {
+            builder.push(word, b"{}").unwrap();
+        }
+        builder.finish().unwrap();
+
+        let index = DictIndex::open(dir).unwrap();
+        let matches = index.fuzzy("recieve", 10);
+        assert_eq!(matches[0].word, "receive");
+        assert_eq!(matches[0].distance, 2);
+        assert!(matches.iter().all(|m| m.word != "house"));
+        assert_eq!(index.fuzzy("hous", 10)[0].distance, 1);
+        assert!(index.fuzzy("ho", 10).is_empty());
+
+        let lookup = index.lookup("HAUS").unwrap().unwrap();
+        assert_eq!(lookup.did_you_mean[0].word, "Haus");
+        assert_eq!(lookup.did_you_mean[0].distance, 0);
+    }
+}
@@ -0,0 +1,260 @@
+use super::definitions::{
+    definition_entry, map_definitions, DefinitionFormat, Record, DECOMPRESSED_FILENAME,
+};
+use super::{BuildPhase, BuildProgress, DictIndexBuilder};
+use flate2::read::GzDecoder;
+use std::error::Error;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+static IFO_MAGIC: &str = "StarDict's dict ifo file";
+/// Entries converted between two checks for cancellation.
+const CHECK_EVERY: usize = 1024;
+
+/// Field types holding text, `h`, `g` (Pango) and `x` (XDXF) being markup. Resources, images and
+/// sounds are left out.
+static TEXT_TYPES: &[u8] = b"mltygxhkw";
+static MARKUP_TYPES: &[u8] = b"hgx";
+
+struct Ifo {
+    offset_bits: u32,
+    same_type_sequence: Option<String>,
+}
+
+fn parse_ifo(ifo: &str) -> Result<Ifo, Box<dyn Error + Send + Sync>> {
+    let mut lines = ifo.lines();
+    if lines.next().map(str::trim) != Some(IFO_MAGIC) {
+        Err("not a StarDict .ifo file")?
+    }
+    let options = lines
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect::<HashMap<&str, &str>>();
+    Ok(Ifo {
+        offset_bits: match options.get("idxoffsetbits") {
+            Some(&"64") => 64,
+            _ => 32,
+        },
+        same_type_sequence: options
+            .get("sametypesequence")
+            .filter(|types| !types.is_empty())
+            .map(|types| types.to_string()),
+    })
+}
+
+/// The `(headword, offset, size)` of every definition listed by an `.idx` file, in file order.
+fn parse_idx(
+    idx: &[u8],
+    offset_bits: u32,
+) -> Result<Vec<Record<String>>, Box<dyn Error + Send + Sync>> {
+    let offset_len = offset_bits as usize / 8;
+    let mut records = Vec::new();
+    let mut rest = idx;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or("truncated .idx file")?;
+        let word = String::from_utf8_lossy(&rest[..end]).into_owned();
+        let numbers = rest
+            .get(end + 1..end + 1 + offset_len + 4)
+            .ok_or("truncated .idx file")?;
+        let (offset, size) = numbers.split_at(offset_len);
+        let offset = offset.iter().fold(0, |n, b| n << 8 | *b as usize);
+        let size = size.iter().fold(0, |n, b| n << 8 | *b as usize);
+        records.push((word, offset, size));
+        rest = &rest[end + 1 + offset_len + 4..];
+    }
+    Ok(records)
+}
+
+/// Splits a definition into its typed fields. With a `sametypesequence` the types are left out of
+/// the data and the last field runs to the end of it.
+fn read_fields<'a>(mut data: &'a [u8], types: Option<&str>) -> Vec<(u8, &'a [u8])> {
+    let mut fields = Vec::new();
+    let mut types = types.map(|types| types.bytes());
+    while !data.is_empty() {
+        let field_type = match &mut types {
+            Some(types) => match types.next() {
+                Some(field_type) => field_type,
+                None => break,
+            },
+            None => {
+                let field_type = data[0];
+                data = &data[1..];
+                field_type
+            }
+        };
+        let is_last = types.as_ref().map_or(false, |types| types.len() == 0);
+        let (field, rest) = if is_last {
+            (data, &data[data.len()..])
+        } else if field_type.is_ascii_lowercase() {
+            let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+            (&data[..end], data.get(end + 1..).unwrap_or_default())
+        } else {
+            let size = match data.get(..4) {
+                Some(size) => size.iter().fold(0, |n, b| n << 8 | *b as usize),
+                None => break,
+            };
+            let end = (4 + size).min(data.len());
+            (&data[4..end], &data[end..])
+        };
+        fields.push((field_type, field));
+        data = rest;
+    }
+    fields
+}
+
+fn read_idx(ifo_path: &Path) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mut idx = Vec::new();
+    match File::open(ifo_path.with_extension("idx")) {
+        Ok(mut file) => file.read_to_end(&mut idx)?,
+        Err(_) => {
+            let file = File::open(ifo_path.with_extension("idx.gz"))
+                .or(Err("index file (.idx or .idx.gz) not found"))?;
+            GzDecoder::new(file).read_to_end(&mut idx)?
+        }
+    };
+    Ok(idx)
+}
+
+/// Builds a dictionary into `dir` from a StarDict dictionary, given the path of its `.ifo` file.
+/// The `.idx` and `.dict` files, gzip- or dictzip-compressed or not, are expected next to it.
+pub fn build_from_stardict(
+    ifo_path: &Path,
+    dir: &str,
+    progress: &BuildProgress,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let ifo = parse_ifo(&fs::read_to_string(ifo_path)?)?;
+    let records = parse_idx(&read_idx(ifo_path)?, ifo.offset_bits)?;
+    fs::create_dir_all(dir)?;
+    let scratch = Path::new(dir).join(DECOMPRESSED_FILENAME);
+    let definitions = map_definitions(ifo_path, &scratch)?;
+    let mut builder = DictIndexBuilder::new(dir)?.with_progress(progress.clone());
+
+    for (i, (word, offset, size)) in records.iter().enumerate() {
+        if i % CHECK_EVERY == 0 {
+            progress.check_canceled()?;
+            progress.report(BuildPhase::Converting, i as u64, records.len() as u64);
+        }
+        let data = offset
+            .checked_add(*size)
+            .and_then(|end| definitions.get(*offset..end))
+            .ok_or("definition out of the .dict file")?;
+        let fields = read_fields(data, ifo.same_type_sequence.as_deref());
+        let format = match fields.iter().any(|(t, _)| MARKUP_TYPES.contains(t)) {
+            true => DefinitionFormat::Html,
+            false => DefinitionFormat::Text,
+        };
+        let definition = fields
+            .iter()
+            .filter(|(t, _)| TEXT_TYPES.contains(t))
+            .map(|(_, field)| String::from_utf8_lossy(field))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !definition.trim().is_empty() {
+            builder.push(word, &definition_entry(&definition, format)?)?;
+        }
+    }
+    drop(definitions);
+    if scratch.exists() {
+        fs::remove_file(&scratch)?;
+    }
+
+    if builder.is_empty() {
+        Err("no dictionary entries found")?
+    }
+    progress.enter(BuildPhase::Indexing);
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_from_stardict, read_fields};
+    use crate::offline_dict::DictIndex;
+    use crate::test_dir::TempDir;
+    use flate2::{write::GzEncoder, Compression};
+    use std::{fs, io::Write};
+
+    #[test]
+    fn fields_are_split_with_and_without_type_sequence() {
+        assert_eq!(
+            read_fields(b"/haus/\0<b>house</b>", Some("th")),
+            vec![(b't', &b"/haus/"[..]), (b'h', &b"<b>house</b>"[..])]
+        );
+        assert_eq!(
+            read_fields(b"mhouse\0W\0\0\0\x02ab", None),
+            vec![(b'm', &b"house"[..]), (b'W', &b"ab"[..])]
+        );
+    }
+
+    #[test]
+    fn dictzip_dictionary_is_imported() {
+        let (src_dir, out_dir) = (TempDir::new("stardict_src"), TempDir::new("stardict"));
+        let (src, dir) = (src_dir.path(), out_dir.path());
+        let definitions = ["<i>n.</i> house<br>building", "to be"];
+        let mut idx = Vec::new();
+        let mut offset = 0u32;
+        for (word, definition) in ["Haus", "sein"].iter().zip(definitions) {
+            idx.extend_from_slice(word.as_bytes());
+            idx.push(0);
+            idx.extend_from_slice(&offset.to_be_bytes());
+            idx.extend_from_slice(&(definition.len() as u32).to_be_bytes());
+            offset += definition.len() as u32;
+        }
+        fs::write(src.join("de.idx"), idx).unwrap();
+        let mut dict = GzEncoder::new(Vec::new(), Compression::default());
+        dict.write_all(definitions.concat().as_bytes()).unwrap();
+        fs::write(src.join("de.dict.dz"), dict.finish().unwrap()).unwrap();
+        let ifo = "StarDict's dict ifo file\nversion=2.4.2\nwordcount=2\nsametypesequence=h\n";
+        fs::write(src.join("de.ifo"), ifo).unwrap();
+
+        let dir_str = dir.to_str().unwrap();
+        build_from_stardict(&src.join("de.ifo"), dir_str, &Default::default()).unwrap();
+        let index = DictIndex::open(dir_str).unwrap();
+        let entry = index.get("Haus").unwrap().unwrap();
+        let definition = entry.as_array().unwrap()[0].get("definition").unwrap();
+        let field = |name| {
+            definition
+                .get(name)
+                .unwrap()
+                .as_string()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(field("format"), "html");
+        assert_eq!(field("text"), definitions[0]);
+        assert!(index.get("sein").unwrap().is_some());
+        assert!(!dir.join("definitions.tmp").exists());
+    }
+
+    #[test]
+    fn definitions_past_the_dict_file_are_rejected() {
+        let (src_dir, out_dir) = (
+            TempDir::new("stardict_overflow_src"),
+            TempDir::new("stardict_overflow"),
+        );
+        let (src, dir) = (src_dir.path(), out_dir.path());
+        let mut idx = b"Haus\0".to_vec();
+        idx.extend_from_slice(&u64::MAX.to_be_bytes());
+        idx.extend_from_slice(&1u32.to_be_bytes());
+        fs::write(src.join("de.idx"), idx).unwrap();
+        fs::write(src.join("de.dict"), "house").unwrap();
+        let ifo = "StarDict's dict ifo file\nversion=3.0.0\nidxoffsetbits=64\nsametypesequence=m\n";
+        fs::write(src.join("de.ifo"), ifo).unwrap();
+
+        let built = build_from_stardict(
+            &src.join("de.ifo"),
+            dir.to_str().unwrap(),
+            &Default::default(),
+        );
+        assert_eq!(
+            built.unwrap_err().to_string(),
+            "definition out of the .dict file"
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use crate::helper::{find_absolute_path, setting_u64, unix_time, CACHE_PATH_WITH_IDENTIFIER};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{fs, future::Future, io, path::PathBuf, sync::Mutex, time::SystemTime};
+
+pub static ONLINE_CACHE_DIR: &str = "online_cache";
+/// Used when `onlineCacheTtlHours` is not set, 0 turns the cache off.
+const DEFAULT_TTL_HOURS: u64 = 7 * 24;
+/// Used when `onlineCacheMaxMb` is not set, 0 turns the cache off.
+const DEFAULT_MAX_MB: u64 = 50;
+
+lazy_static! {
+    pub static ref ONLINE_CACHE: OnlineCache = OnlineCache::new(PathBuf::from(find_absolute_path(
+        &CACHE_PATH_WITH_IDENTIFIER,
+        ONLINE_CACHE_DIR
+    )));
+}
+
+/// What a result is cached under.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct CacheKey {
+    pub provider: String,
+    /// See `TranslationProvider::cache_variant`.
+    #[serde(default)]
+    pub variant: String,
+    pub from: String,
+    pub to: String,
+    pub text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedResult {
+    #[serde(flatten)]
+    key: CacheKey,
+    /// Seconds since the Unix epoch.
+    stored_at: u64,
+    result: Value,
+}
+
+/// Lowercased, trimmed and with runs of whitespace collapsed, so that lookups differing only in
+/// these share an entry.
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Results of the online providers kept on disk, one file per provider variant, language pair and text.
+/// Once the files add up to more than the size cap, the least recently written ones are removed.
+pub struct OnlineCache {
+    dir: PathBuf,
+    /// Bytes taken by the cache, counted on first write.
+    size: Mutex<Option<u64>>,
+}
+
+impl OnlineCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            size: Mutex::new(None),
+        }
+    }
+
+    fn path(&self, key: &CacheKey) -> PathBuf {
+        let hash = Sha256::new()
+            .chain_update(&key.provider)
+            .chain_update([0])
+            .chain_update(&key.variant)
+            .chain_update([0])
+            .chain_update(&key.from)
+            .chain_update([0])
+            .chain_update(&key.to)
+            .chain_update([0])
+            .chain_update(&key.text)
+            .finalize();
+        self.dir.join(format!("{hash:x}.json"))
+    }
+
+    /// The stored result and whether it is still younger than `ttl` seconds.
+    pub fn get(&self, key: &CacheKey, ttl: u64) -> Option<(Value, bool)> {
+        let file = fs::read(self.path(key)).ok()?;
+        let cached = serde_json::from_slice::<CachedResult>(&file).ok()?;
+        // a hash collision is as good as a miss.
+        if &cached.key != key {
+            return None;
+        }
+        let fresh = cached.stored_at + ttl > unix_time();
+        Some((cached.result, fresh))
+    }
+
+    pub fn put(&self, key: CacheKey, result: Value, max_bytes: u64) -> io::Result<()> {
+        let path = self.path(&key);
+        let cached = CachedResult {
+            key,
+            stored_at: unix_time(),
+            result,
+        };
+        let file = serde_json::to_vec(&cached)?;
+        let mut size = self.size.lock().unwrap();
+        fs::create_dir_all(&self.dir)?;
+        let total = match *size {
+            Some(total) => total,
+            None => self.files()?.iter().map(|(_, len, _)| len).sum(),
+        };
+        fs::write(path, &file)?;
+        let mut total = total + file.len() as u64;
+        if total > max_bytes {
+            total = self.evict(max_bytes)?;
+        }
+        *size = Some(total);
+        Ok(())
+    }
+
+    /// Every cached file with its length and modification time.
+    fn files(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if meta.is_file() {
+                files.push((entry.path(), meta.len(), meta.modified()?));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Removes the oldest files until the rest fit in `max_bytes`, and returns their size.
+    fn evict(&self, max_bytes: u64) -> io::Result<u64> {
+        let mut files = self.files()?;
+        let mut total = files.iter().map(|(_, len, _)| len).sum::<u64>();
+        files.sort_by_key(|(_, _, modified)| *modified);
+        for (path, len, _) in files {
+            if total <= max_bytes {
+                break;
+            }
+            fs::remove_file(path)?;
+            total -= len;
+        }
+        Ok(total)
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        let mut size = self.size.lock().unwrap();
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+        *size = Some(0);
+        Ok(())
+    }
+}
+
+fn is_empty(result: &Value) -> bool {
+    match result {
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Null => true,
+        _ => false,
+    }
+}
+
+/// The result of `provider` for `text`, from the cache while it is fresh or from `fetch`, along with
+/// whether a fetched result could be stored. When fetching fails, an expired result is better than
+/// none, so that lookups keep working offline.
+pub async fn cached<T, E, F>(
+    provider: &str,
+    variant: &str,
+    from: &str,
+    to: &str,
+    text: &str,
+    fetch: F,
+) -> (Result<T, E>, io::Result<()>)
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T, E>>,
+{
+    let ttl = setting_u64("onlineCacheTtlHours").unwrap_or(DEFAULT_TTL_HOURS) * 60 * 60;
+    let max_bytes = setting_u64("onlineCacheMaxMb").unwrap_or(DEFAULT_MAX_MB) * 1024 * 1024;
+    if ttl == 0 || max_bytes == 0 {
+        return (fetch.await, Ok(()));
+    }
+    let key = CacheKey {
+        provider: provider.to_owned(),
+        variant: variant.to_owned(),
+        from: from.to_owned(),
+        to: to.to_owned(),
+        text: normalize(text),
+    };
+    let stored = ONLINE_CACHE
+        .get(&key, ttl)
+        .and_then(|(result, fresh)| Some((serde_json::from_value::<T>(result).ok()?, fresh)));
+    let stale = match stored {
+        Some((result, true)) => return (Ok(result), Ok(())),
+        Some((result, false)) => Some(result),
+        None => None,
+    };
+    match fetch.await {
+        Ok(result) => {
+            let stored = match serde_json::to_value(&result) {
+                Ok(value) if !is_empty(&value) => ONLINE_CACHE.put(key, value, max_bytes),
+                _ => Ok(()),
+            };
+            (Ok(result), stored)
+        }
+        Err(e) => (stale.ok_or(e), Ok(())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, CacheKey, OnlineCache};
+    use crate::test_dir::TempDir;
+    use serde_json::json;
+
+    fn key(variant: &str, to: &str) -> CacheKey {
+        CacheKey {
+            provider: "deepl".to_owned(),
+            variant: variant.to_owned(),
+            from: "en".to_owned(),
+            to: to.to_owned(),
+            text: normalize("  Hello\n  World "),
+        }
+    }
+
+    #[test]
+    fn results_are_cached_within_ttl_and_size() {
+        let tmp = TempDir::new("online_cache");
+        let dir = tmp.path().join("cache");
+        let cache = OnlineCache::new(dir.clone());
+        cache.clear().unwrap();
+        assert_eq!(key("", "de").text, "hello world");
+
+        cache.put(key("", "de"), json!("Hallo Welt"), 750).unwrap();
+        assert_eq!(
+            cache.get(&key("", "de"), 60),
+            Some((json!("Hallo Welt"), true))
+        );
+        assert_eq!(
+            cache.get(&key("", "de"), 0),
+            Some((json!("Hallo Welt"), false))
+        );
+        assert_eq!(cache.get(&key("", "fr"), 60), None);
+        assert_eq!(cache.get(&key("prefer_more", "de"), 60), None);
+
+        let long = json!("x".repeat(600));
+        cache.put(key("", "fr"), long, 750).unwrap();
+        assert_eq!(cache.get(&key("", "de"), 60), None);
+        assert!(cache.get(&key("", "fr"), 60).is_some());
+        cache.clear().unwrap();
+        assert!(!dir.exists());
+    }
+}
@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
+use futures_util::future::{BoxFuture, FutureExt};
 use scraper::Html;
 
-use super::CLIENT;
+use super::{throttle::send, ProviderError, ProviderOutput, TranslationProvider};
+use crate::http::client;
 
 lazy_static! {
   static ref SEMI_BIL_CAMBRIDGE_DICTS: HashMap<&'static str, &'static str> = HashMap::from([
@@ -52,35 +54,50 @@ lazy_static! {
   ]);
 }
 
-pub struct Translator<'a> {
-    pub to: &'a str,
-    pub from: &'a str,
-}
+pub struct CambridgeProvider;
 
-impl Translator<'_> {
-    pub async fn translate(&self, text: &str) -> Result<String, String> {
-        if text.contains(" ") || (self.from != "en" && self.from == self.to) {
-            return Ok("".to_string());
+impl TranslationProvider for CambridgeProvider {
+    fn name(&self) -> &'static str {
+        "cambridge"
+    }
+
+    fn supports(&self, from: &str, to: &str) -> bool {
+        (from == "auto" || CAMBRIDGE_DICTS.contains_key(from)) && (from == "en" || from != to)
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>> {
+        async move {
+            if text.contains(" ") {
+                return Ok(ProviderOutput::Text("".to_string()));
+            }
+            parse_result(fetch_page(text, from, to).await).map(ProviderOutput::Text)
         }
-        self.parse_result(fetch_page(text, self.from, self.to).await)
+        .boxed()
     }
+}
 
-    fn parse_result(&self, result: Result<String, reqwest::Error>) -> Result<String, String> {
-        match result {
-            Ok(body) => {
-                let html = scraper::Html::parse_document(&body);
-                let mut res = parse_entry_body_html(&html);
-                if &res == "" {
-                    res = parse_kdic_html(&html);
-                }
-                Ok(res)
+fn parse_result(result: Result<String, ProviderError>) -> Result<String, ProviderError> {
+    match result {
+        Ok(body) => {
+            let html = scraper::Html::parse_document(&body);
+            let mut res = parse_entry_body_html(&html);
+            if res.is_empty() {
+                res = parse_kdic_html(&html);
             }
-            Err(err) => return Err(err.to_string()),
+            Ok(res)
         }
+        Err(err) => Err(err),
     }
 }
 
-async fn fetch_page(text: &str, from: &str, to: &str) -> Result<String, reqwest::Error> {
+async fn fetch_page(text: &str, from: &str, to: &str) -> Result<String, ProviderError> {
+    // entries are found under their lowercased headword.
+    let text = text.to_lowercase();
     let formatted_url: String;
     let from_eq = if from == "auto" {
         *CAMBRIDGE_DICTS.get("en").unwrap()
@@ -123,7 +140,11 @@ async fn fetch_page(text: &str, from: &str, to: &str) -> Result<String, reqwest:
         }
     }
 
-    let content = CLIENT.get(formatted_url).send().await?.text().await?;
+    let content = send(CambridgeProvider.name(), || client().get(&formatted_url))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
     Ok(content)
 }
 
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use futures_util::future::{BoxFuture, FutureExt};
+use reqwest::header::AUTHORIZATION;
+use serde::Deserialize;
+
+use super::{throttle::send, ProviderError, ProviderOutput, TranslationProvider};
+use crate::{helper::setting_str, http::client};
+
+static FREE_ENDPOINT: &str = "https://api-free.deepl.com/v2/translate";
+static PRO_ENDPOINT: &str = "https://api.deepl.com/v2/translate";
+
+lazy_static! {
+  /// The source and target codes DeepL has for the app languages it supports.
+  static ref DEEPL_CODES: HashMap<&'static str, (&'static str, &'static str)> = HashMap::from([
+    ("ar", ("AR", "AR")),
+    ("bg", ("BG", "BG")),
+    ("cs", ("CS", "CS")),
+    ("da", ("DA", "DA")),
+    ("de", ("DE", "DE")),
+    ("el", ("EL", "EL")),
+    ("en", ("EN", "EN-US")),
+    ("es", ("ES", "ES")),
+    ("et", ("ET", "ET")),
+    ("fi", ("FI", "FI")),
+    ("fr", ("FR", "FR")),
+    ("hu", ("HU", "HU")),
+    ("id", ("ID", "ID")),
+    ("it", ("IT", "IT")),
+    ("ja", ("JA", "JA")),
+    ("ko", ("KO", "KO")),
+    ("lt", ("LT", "LT")),
+    ("lv", ("LV", "LV")),
+    ("nl", ("NL", "NL")),
+    ("no", ("NB", "NB")),
+    ("pl", ("PL", "PL")),
+    ("pt", ("PT", "PT-BR")),
+    ("ro", ("RO", "RO")),
+    ("ru", ("RU", "RU")),
+    ("sk", ("SK", "SK")),
+    ("sl", ("SL", "SL")),
+    ("sv", ("SV", "SV")),
+    ("tr", ("TR", "TR")),
+    ("uk", ("UK", "UK")),
+    ("zh-CN", ("ZH", "ZH-HANS")),
+    ("zh-TW", ("ZH", "ZH-HANT")),
+  ]);
+}
+
+#[derive(Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    text: String,
+}
+
+/// Asks the DeepL API with the key in `deeplApiKey`, it is only enabled once a key is set.
+pub struct DeepLProvider;
+
+impl TranslationProvider for DeepLProvider {
+    fn name(&self) -> &'static str {
+        "deepl"
+    }
+
+    fn supports(&self, from: &str, to: &str) -> bool {
+        from != to
+            && (from == "auto" || DEEPL_CODES.contains_key(from))
+            && DEEPL_CODES.contains_key(to)
+            && setting_str("deeplApiKey").is_some()
+    }
+
+    fn cache_variant(&self) -> String {
+        formality().unwrap_or_default().to_owned()
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>> {
+        async move {
+            let key = setting_str("deeplApiKey").unwrap_or_default();
+            let mut form = vec![("text", text)];
+            if let Some((_, target)) = DEEPL_CODES.get(to) {
+                form.push(("target_lang", *target));
+            }
+            if let Some((source, _)) = DEEPL_CODES.get(from) {
+                form.push(("source_lang", *source));
+            }
+            if let Some(formality) = formality() {
+                form.push(("formality", formality));
+            }
+            let body = send(self.name(), || {
+                client()
+                    .post(endpoint(&key))
+                    .header(AUTHORIZATION, format!("DeepL-Auth-Key {key}"))
+                    .form(&form)
+            })
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+            parse_response(&body).map(ProviderOutput::Text)
+        }
+        .boxed()
+    }
+}
+
+/// Keys of the free API end with `:fx`.
+fn endpoint(key: &str) -> &'static str {
+    if key.ends_with(":fx") {
+        FREE_ENDPOINT
+    } else {
+        PRO_ENDPOINT
+    }
+}
+
+/// The `deeplFormality` setting, `more` or `less`. DeepL is asked to prefer it, so that languages
+/// without formality are still translated.
+fn formality() -> Option<&'static str> {
+    match setting_str("deeplFormality")?.as_str() {
+        "more" | "prefer_more" => Some("prefer_more"),
+        "less" | "prefer_less" => Some("prefer_less"),
+        _ => None,
+    }
+}
+
+fn parse_response(body: &str) -> Result<String, ProviderError> {
+    let response = serde_json::from_str::<DeepLResponse>(body)?;
+    Ok(response
+        .translations
+        .into_iter()
+        .map(|translation| translation.text)
+        .collect::<Vec<String>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{endpoint, parse_response, DEEPL_CODES, FREE_ENDPOINT, PRO_ENDPOINT};
+
+    #[test]
+    fn requests_and_responses_follow_the_api() {
+        assert_eq!(DEEPL_CODES["en"], ("EN", "EN-US"));
+        assert_eq!(DEEPL_CODES["zh-TW"], ("ZH", "ZH-HANT"));
+        assert!(!DEEPL_CODES.contains_key("fa"));
+        assert_eq!(endpoint("0123-abcd:fx"), FREE_ENDPOINT);
+        assert_eq!(endpoint("0123-abcd"), PRO_ENDPOINT);
+
+        let body = r#"{"translations":[{"detected_source_language":"EN","text":"Hallo Welt"}]}"#;
+        assert_eq!(parse_response(body).unwrap(), "Hallo Welt");
+        assert!(parse_response(r#"{"message":"Quota exceeded"}"#).is_err());
+    }
+}
@@ -1,34 +1,54 @@
-use super::CLIENT;
+use super::{throttle::send, ProviderError, ProviderOutput, TranslationProvider};
+use crate::http::client;
+use futures_util::future::{BoxFuture, FutureExt};
 
-pub struct Translator<'a> {
-    pub to: &'a str,
-    pub from: &'a str,
-}
+pub struct GoogleProvider;
+
+impl TranslationProvider for GoogleProvider {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn supports(&self, _from: &str, _to: &str) -> bool {
+        true
+    }
 
-impl Translator<'_> {
-    pub async fn translate(&self, text: &str) -> Result<String, String> {
-        parse_result(fetch_page(text, self.from, self.to).await)
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>> {
+        async move { parse_result(fetch_page(text, from, to).await).map(ProviderOutput::Text) }
+            .boxed()
     }
 }
 
-async fn fetch_page(text: &str, from: &str, to: &str) -> Result<String, reqwest::Error> {
+async fn fetch_page(text: &str, from: &str, to: &str) -> Result<String, ProviderError> {
     let formatted_url = format!(
         "https://translate.google.com/m?tl={}&sl={}&q={}",
         to, from, text
     );
-    let content = CLIENT.get(formatted_url).send().await?.text().await?;
+    let content = send(GoogleProvider.name(), || client().get(&formatted_url))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
     Ok(content)
 }
 
-fn parse_result(result: Result<String, reqwest::Error>) -> Result<String, String> {
+fn parse_result(result: Result<String, ProviderError>) -> Result<String, ProviderError> {
     match result {
         Ok(body) => {
             let html = scraper::Html::parse_document(&body);
             let selector = scraper::Selector::parse(".result-container").unwrap();
             let mut nodes = html.select(&selector);
 
-            Ok(nodes.nth(0).unwrap().text().collect::<Vec<&str>>().concat())
+            let node = nodes.next().ok_or_else(|| ProviderError::Parse {
+                message: "no translation in the page".to_owned(),
+            })?;
+            Ok(node.text().collect::<Vec<&str>>().concat())
         }
-        Err(err) => return Err(err.to_string()),
+        Err(err) => Err(err),
     }
 }
@@ -1,18 +1,52 @@
+mod cache;
 mod cambridge_translate;
+mod deepl_translate;
 mod google_translate;
 mod other_online_translate;
+mod provider;
+mod throttle;
 
-use self::other_online_translate::{MyMemoryTranslation, OtherTranslator};
-use serde::Serialize;
-use std::{thread, time::Duration};
-use tauri::async_runtime::block_on;
+use self::cache::cached;
+pub use self::cache::ONLINE_CACHE;
+use self::provider::provider_timeout;
+pub use self::provider::{enabled_providers, ProviderError, ProviderOutput, TranslationProvider};
+use futures_util::future::{abortable, join_all, AbortHandle};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 
 lazy_static! {
-    pub static ref CLIENT: reqwest::Client = reqwest::Client::builder()
-        .connect_timeout(Duration::from_secs(10))
-        .use_rustls_tls()
-        .build()
-        .unwrap();
+    /// The lookup running for each window, see `supersede`.
+    static ref IN_FLIGHT: Mutex<HashMap<String, (u64, AbortHandle)>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_LOOKUP: AtomicU64 = AtomicU64::new(0);
+
+/// Runs `lookup` as the latest one of `window`, aborting the lookup it supersedes. Returns `None`
+/// when a newer lookup of the same window supersedes this one before it completes.
+pub async fn supersede<F: Future>(window: &str, lookup: F) -> Option<F::Output> {
+    let id = NEXT_LOOKUP.fetch_add(1, Ordering::Relaxed);
+    let (lookup, handle) = abortable(lookup);
+    if let Some((_, older)) = IN_FLIGHT
+        .lock()
+        .unwrap()
+        .insert(window.to_owned(), (id, handle))
+    {
+        older.abort();
+    }
+    let output = lookup.await.ok();
+    let mut in_flight = IN_FLIGHT.lock().unwrap();
+    if matches!(in_flight.get(window), Some((latest, _)) if *latest == id) {
+        in_flight.remove(window);
+    }
+    output
 }
 
 pub struct OnlineTranslator<'a> {
@@ -20,80 +54,184 @@ pub struct OnlineTranslator<'a> {
     pub from: &'a str,
 }
 
+/// A provider that was asked and failed.
 #[derive(Serialize)]
+struct ProviderFailure<'a> {
+    provider: &'static str,
+    #[serde(flatten)]
+    error: &'a ProviderError,
+}
+
+/// The outcome of every provider asked, in the order the providers are enabled. Serialized as an
+/// object with the output of each provider that succeeded under its name, and an `errors` list
+/// of the providers that failed.
 pub struct OnlineTranslation {
-    google: String,
-    cambridge: String,
-    sentencedict: String,
-    mymemory: Vec<MyMemoryTranslation>,
+    results: Vec<(&'static str, Result<ProviderOutput, ProviderError>)>,
+    /// The first error in storing an output in the cache, which does not fail the lookup.
+    cache_error: Option<io::Error>,
 }
 
-impl OnlineTranslator<'_> {
-    pub async fn translate(&self, text: &str) -> Result<OnlineTranslation, String> {
-        let text = text.trim().to_lowercase();
-        thread::scope(|s| {
-            let google_s = s.spawn(|| {
-                block_on(async {
-                    google_translate::Translator {
-                        from: self.from,
-                        to: self.to,
-                    }
-                    .translate(&text)
-                    .await
-                })
-            });
+impl OnlineTranslation {
+    /// Whether at least one provider has an output.
+    pub fn found(&self) -> bool {
+        self.results.iter().any(|(_, result)| result.is_ok())
+    }
 
-            let sentencedict_s = s.spawn(|| {
-                block_on(async {
-                    if self.from == "auto" || self.from == "en" {
-                        OtherTranslator::sentencedict_translate(&text).await
-                    } else {
-                        Ok("".to_string())
-                    }
-                })
-            });
+    pub fn cache_error(&self) -> Option<&io::Error> {
+        self.cache_error.as_ref()
+    }
+}
 
-            let cambridge_s = s.spawn(|| {
-                block_on(async {
-                    cambridge_translate::Translator {
-                        from: self.from,
-                        to: self.to,
-                    }
-                    .translate(&text)
-                    .await
-                })
-            });
+impl Serialize for OnlineTranslation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        let mut errors = Vec::new();
+        for (provider, result) in &self.results {
+            match result {
+                Ok(output) => map.serialize_entry(provider, output)?,
+                Err(error) => errors.push(ProviderFailure { provider, error }),
+            }
+        }
+        map.serialize_entry("errors", &errors)?;
+        map.end()
+    }
+}
+
+/// Payload of the `online_result` event, the outcome of one provider for a streamed lookup.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderResultPayload<'a> {
+    request_id: u64,
+    provider: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<&'a ProviderOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a ProviderError>,
+}
 
-            let mymemory_s = s.spawn(|| {
-                block_on(async {
-                    OtherTranslator::mymemory_translate(&text, self.from, self.to).await
-                })
+impl<'a> ProviderResultPayload<'a> {
+    pub fn new(
+        request_id: u64,
+        provider: &'static str,
+        result: &'a Result<ProviderOutput, ProviderError>,
+    ) -> Self {
+        Self {
+            request_id,
+            provider,
+            output: result.as_ref().ok(),
+            error: result.as_ref().err(),
+        }
+    }
+}
+
+/// Payload of the `online_done` event, sent once every provider of a streamed lookup has answered.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OnlineDonePayload {
+    pub request_id: u64,
+    pub found: bool,
+}
+
+impl OnlineTranslator<'_> {
+    pub async fn translate(&self, text: &str) -> OnlineTranslation {
+        self.translate_each(text, |_, _| {}).await
+    }
+
+    /// Like `translate`, also handing each outcome to `on_result` as soon as its provider answers.
+    /// Every provider is given the time set for it in the settings, see `provider_timeout`.
+    pub async fn translate_each<F>(&self, text: &str, on_result: F) -> OnlineTranslation
+    where
+        F: Fn(&'static str, &Result<ProviderOutput, ProviderError>),
+    {
+        let text = text.trim();
+        let lookups = enabled_providers()
+            .into_iter()
+            .filter(|provider| provider.supports(self.from, self.to))
+            .map(|provider| {
+                let on_result = &on_result;
+                async move {
+                    let name = provider.name();
+                    let fetch = provider.translate(text, self.from, self.to);
+                    let fetch = async move {
+                        match provider_timeout(name) {
+                            Some(limit) => tokio::time::timeout(limit, fetch).await.unwrap_or(Err(
+                                ProviderError::Timeout {
+                                    seconds: limit.as_secs(),
+                                },
+                            )),
+                            None => fetch.await,
+                        }
+                    };
+                    let variant = provider.cache_variant();
+                    let (result, stored) =
+                        cached(name, &variant, self.from, self.to, text, fetch).await;
+                    on_result(name, &result);
+                    (name, result, stored)
+                }
             });
+        let mut cache_error = None;
+        let results = join_all(lookups)
+            .await
+            .into_iter()
+            .map(|(name, result, stored)| {
+                if let Err(e) = stored {
+                    cache_error.get_or_insert(e);
+                }
+                (name, result)
+            })
+            .collect();
+        OnlineTranslation {
+            results,
+            cache_error,
+        }
+    }
+}
 
-            let google = google_s.join().unwrap();
-            let cambridge = cambridge_s.join().unwrap();
-            let sentencedict = sentencedict_s.join().unwrap();
-            let mymemory = mymemory_s.join().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::{
+        supersede, OnlineTranslation, ProviderError, ProviderOutput, ProviderResultPayload,
+    };
+    use futures_util::future::{join, pending};
+    use serde_json::json;
+    use tauri::async_runtime::block_on;
 
-            if let Err(e) = google {
-                return Err(e);
-            }
-            if let Err(e) = cambridge {
-                return Err(e);
-            }
-            if let Err(e) = mymemory {
-                return Err(e);
-            }
-            if let Err(e) = sentencedict {
-                return Err(e);
-            }
+    #[test]
+    fn failed_providers_are_listed_with_the_results() {
+        let translation = OnlineTranslation {
+            results: vec![
+                ("google", Ok(ProviderOutput::Text("Haus".to_owned()))),
+                ("mymemory", Err(ProviderError::Http { status: 429 })),
+            ],
+            cache_error: None,
+        };
+        assert!(translation.found());
+        assert_eq!(
+            serde_json::to_value(&translation).unwrap(),
+            json!({
+                "google": "Haus",
+                "errors": [{"provider": "mymemory", "kind": "http", "status": 429}],
+            })
+        );
 
-            Ok(OnlineTranslation {
-                google: google.unwrap(),
-                cambridge: cambridge.unwrap(),
-                mymemory: mymemory.unwrap(),
-                sentencedict: sentencedict.unwrap(),
+        let (provider, result) = &translation.results[1];
+        assert_eq!(
+            serde_json::to_value(ProviderResultPayload::new(7, provider, result)).unwrap(),
+            json!({
+                "requestId": 7,
+                "provider": "mymemory",
+                "error": {"kind": "http", "status": 429},
             })
-        })
+        );
+    }
+
+    #[test]
+    fn newer_lookups_supersede_older_ones() {
+        let (older, newer) = block_on(join(
+            supersede("main", pending::<()>()),
+            supersede("main", async { "Haus" }),
+        ));
+        assert_eq!((older, newer), (None, Some("Haus")));
+        assert_eq!(block_on(supersede("main", async { 1 })), Some(1));
     }
 }
@@ -1,23 +1,72 @@
-use super::CLIENT;
+use super::{throttle::send, ProviderError, ProviderOutput, TranslationProvider};
+use crate::http::client;
+use futures_util::future::{BoxFuture, FutureExt};
 use serde::{Deserialize, Serialize};
 
 pub struct OtherTranslator {}
 
+pub struct SentencedictProvider;
+
+impl TranslationProvider for SentencedictProvider {
+    fn name(&self) -> &'static str {
+        "sentencedict"
+    }
+
+    fn supports(&self, from: &str, _to: &str) -> bool {
+        from == "auto" || from == "en"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        _from: &'a str,
+        _to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>> {
+        OtherTranslator::sentencedict_translate(text)
+            .map(|result| result.map(ProviderOutput::Text))
+            .boxed()
+    }
+}
+
+pub struct MyMemoryProvider;
+
+impl TranslationProvider for MyMemoryProvider {
+    fn name(&self) -> &'static str {
+        "mymemory"
+    }
+
+    fn supports(&self, from: &str, to: &str) -> bool {
+        (if from == "auto" { "en" } else { from }) != to
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>> {
+        OtherTranslator::mymemory_translate(text, from, to)
+            .map(|result| result.map(ProviderOutput::Matches))
+            .boxed()
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct MyMemoryModel {
     matches: Vec<MyMemoryTranslation>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct MyMemoryTranslation {
     segment: String,
     translation: String,
-    #[serde(rename(deserialize = "match"))]
+    // cached results are read back under the serialized name.
+    #[serde(rename(deserialize = "match"), alias = "accuracy")]
     accuracy: f32,
 }
 
 impl OtherTranslator {
-    pub async fn sentencedict_translate(text: &str) -> Result<String, String> {
+    pub async fn sentencedict_translate(text: &str) -> Result<String, ProviderError> {
         if text.contains(" ") {
             return Ok("".to_string());
         }
@@ -28,18 +77,26 @@ impl OtherTranslator {
         text: &str,
         from: &str,
         to: &str,
-    ) -> Result<Vec<MyMemoryTranslation>, String> {
+    ) -> Result<Vec<MyMemoryTranslation>, ProviderError> {
         parse_mymemory_resp(fetch_mymemory(text, from, to).await)
     }
 }
 
-async fn fetch_sentencedict_page(text: &str) -> Result<String, reqwest::Error> {
-    let formatted_url = format!("https://sentencedict.com/{}.html", text.trim());
-    let content = CLIENT.get(formatted_url).send().await?.text().await?;
+async fn fetch_sentencedict_page(text: &str) -> Result<String, ProviderError> {
+    // pages are found under the lowercased word.
+    let formatted_url = format!(
+        "https://sentencedict.com/{}.html",
+        text.trim().to_lowercase()
+    );
+    let content = send(SentencedictProvider.name(), || client().get(&formatted_url))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
     Ok(content)
 }
 
-async fn fetch_mymemory(text: &str, from: &str, to: &str) -> Result<String, reqwest::Error> {
+async fn fetch_mymemory(text: &str, from: &str, to: &str) -> Result<String, ProviderError> {
     let fr = if from == "auto" { "en" } else { from };
 
     if fr == to {
@@ -50,43 +107,43 @@ async fn fetch_mymemory(text: &str, from: &str, to: &str) -> Result<String, reqw
         "https://api.mymemory.translated.net/get?q={}&langpair={}|{}",
         text, fr, to
     );
-    let content = CLIENT.get(formatted_url).send().await?.text().await?;
+    let content = send(MyMemoryProvider.name(), || client().get(&formatted_url))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
     Ok(content)
 }
 
 fn parse_mymemory_resp(
-    result: Result<String, reqwest::Error>,
-) -> Result<Vec<MyMemoryTranslation>, String> {
+    result: Result<String, ProviderError>,
+) -> Result<Vec<MyMemoryTranslation>, ProviderError> {
     match result {
         Ok(body) => {
-            if &body == "" {
+            if body.is_empty() {
                 return Ok(vec![]);
             }
 
-            let json_body = serde_json::from_str::<MyMemoryModel>(&body);
-            if let Err(json_err) = json_body {
-                Err(json_err.to_string())
-            } else {
-                Ok(json_body.unwrap().matches)
-            }
+            let json_body = serde_json::from_str::<MyMemoryModel>(&body)?;
+            Ok(json_body.matches)
         }
-        Err(err) => return Err(err.to_string()),
+        Err(err) => Err(err),
     }
 }
 
-fn parse_sentencedict_resp(result: Result<String, reqwest::Error>) -> Result<String, String> {
+fn parse_sentencedict_resp(result: Result<String, ProviderError>) -> Result<String, ProviderError> {
     match result {
         Ok(body) => {
             let all = body
                 .split("<!--最大高度为105px,能显示5行多的样子-->")
                 .nth(1);
-            if let None = all {
+            if all.is_none() {
                 return Ok("".to_string());
             }
 
-            let res = all.unwrap().split("<!--all结束-->").nth(0).unwrap_or("");
+            let res = all.unwrap().split("<!--all结束-->").next().unwrap_or("");
             Ok(res.trim().to_string())
         }
-        Err(err) => return Err(err.to_string()),
+        Err(err) => Err(err),
     }
 }
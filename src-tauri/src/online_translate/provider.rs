@@ -0,0 +1,147 @@
+use super::cambridge_translate::CambridgeProvider;
+use super::deepl_translate::DeepLProvider;
+use super::google_translate::GoogleProvider;
+use super::other_online_translate::{MyMemoryProvider, MyMemoryTranslation, SentencedictProvider};
+use crate::helper::{setting_str_list, setting_u64, setting_u64_entry};
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Used when neither `onlineProviderTimeoutSecs.{name}` nor `onlineTimeoutSecs` is set.
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// What a provider found, serialized as it is under the name of the provider.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ProviderOutput {
+    /// A translation, or a page of definitions as HTML.
+    Text(String),
+    /// Translation memory matches.
+    Matches(Vec<MyMemoryTranslation>),
+}
+
+/// Why a provider has no output.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ProviderError {
+    /// The service could not be reached or the answer could not be read.
+    Network { message: String },
+    /// The service answered with an error status.
+    Http { status: u16 },
+    /// The answer was not in the shape the provider expects.
+    Parse { message: String },
+    /// The service did not answer within the time given to the provider.
+    Timeout { seconds: u64 },
+    /// The provider was asked too often, by the rate limit in the settings or by the service.
+    #[serde(rename = "rateLimited", rename_all = "camelCase")]
+    RateLimited { retry_after_secs: Option<u64> },
+}
+
+impl From<reqwest::Error> for ProviderError {
+    fn from(err: reqwest::Error) -> Self {
+        if let Some(status) = err.status() {
+            ProviderError::Http {
+                status: status.as_u16(),
+            }
+        } else if err.is_decode() {
+            ProviderError::Parse {
+                message: err.to_string(),
+            }
+        } else {
+            ProviderError::Network {
+                message: err.to_string(),
+            }
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProviderError {
+    fn from(err: serde_json::Error) -> Self {
+        ProviderError::Parse {
+            message: err.to_string(),
+        }
+    }
+}
+
+/// An online service `OnlineTranslator` asks for translations.
+pub trait TranslationProvider: Send + Sync {
+    /// Key of the results of the provider, also used in the `onlineProviders` setting.
+    fn name(&self) -> &'static str;
+
+    /// Whether the provider has anything for this language pair, `from` being `auto` when the
+    /// language is to be detected.
+    fn supports(&self, from: &str, to: &str) -> bool;
+
+    /// The options of the provider that change its results, e.g. the formality asked of DeepL.
+    /// Results are cached apart for each of them.
+    fn cache_variant(&self) -> String {
+        String::new()
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxFuture<'a, Result<ProviderOutput, ProviderError>>;
+}
+
+lazy_static! {
+    /// Every known provider, in the order they are asked when `onlineProviders` is not set.
+    static ref PROVIDERS: Vec<Box<dyn TranslationProvider>> = vec![
+        Box::new(GoogleProvider),
+        Box::new(DeepLProvider),
+        Box::new(CambridgeProvider),
+        Box::new(SentencedictProvider),
+        Box::new(MyMemoryProvider),
+    ];
+}
+
+fn find_provider(name: &str) -> Option<&'static dyn TranslationProvider> {
+    PROVIDERS
+        .iter()
+        .find(|provider| provider.name() == name)
+        .map(|provider| provider.as_ref())
+}
+
+/// The providers listed in the `onlineProviders` setting, in its order, or all of them.
+/// Unknown and repeated names are ignored.
+pub fn enabled_providers() -> Vec<&'static dyn TranslationProvider> {
+    let names = match setting_str_list("onlineProviders") {
+        Some(names) => names,
+        None => return PROVIDERS.iter().map(|provider| provider.as_ref()).collect(),
+    };
+    let mut enabled = Vec::<&'static dyn TranslationProvider>::new();
+    for provider in names.iter().filter_map(|name| find_provider(name)) {
+        if !enabled.iter().any(|e| e.name() == provider.name()) {
+            enabled.push(provider);
+        }
+    }
+    enabled
+}
+
+/// The time the provider named `name` is given to answer, from `onlineProviderTimeoutSecs.{name}`,
+/// then `onlineTimeoutSecs`. A timeout of 0 lets the provider take as long as it needs.
+pub fn provider_timeout(name: &str) -> Option<Duration> {
+    let seconds = setting_u64_entry("onlineProviderTimeoutSecs", name)
+        .or_else(|| setting_u64("onlineTimeoutSecs"))
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProviderOutput;
+    use serde_json::json;
+
+    #[test]
+    fn outputs_read_back_as_serialized() {
+        let matches = json!([{"segment": "Haus", "translation": "house", "accuracy": 0.75}]);
+        let output = serde_json::from_value::<ProviderOutput>(matches.clone()).unwrap();
+        assert!(matches!(output, ProviderOutput::Matches(_)));
+        assert_eq!(serde_json::to_value(output).unwrap(), matches);
+
+        let text = serde_json::from_value::<ProviderOutput>(json!("house")).unwrap();
+        assert!(matches!(text, ProviderOutput::Text(ref t) if t == "house"));
+    }
+}
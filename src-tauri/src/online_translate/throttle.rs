@@ -0,0 +1,253 @@
+use super::{provider::provider_timeout, ProviderError};
+use crate::helper::setting_u64_entry;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Used when `onlineRatePerMinute.{provider}` is not set, 0 turns the rate limit off.
+const DEFAULT_RATE_PER_MINUTE: u64 = 30;
+/// Used when `onlineRateBurst.{provider}` is not set.
+const DEFAULT_BURST: u64 = 5;
+/// `(provider, rate per minute, burst)` of the providers that tolerate less, or more, than the
+/// defaults above. The free endpoints of Google and MyMemory block clients asking too often.
+static PROVIDER_DEFAULTS: &[(&str, u64, u64)] =
+    &[("google", 15, 3), ("mymemory", 10, 2), ("deepl", 60, 10)];
+/// A request is held back this long at most for its rate limit, it fails as rate limited beyond.
+const MAX_ACQUIRE_WAIT: Duration = Duration::from_secs(2);
+/// Retries of a request answered with 429 or a server error.
+const MAX_RETRIES: u32 = 2;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+/// A `Retry-After` longer than this is not waited for, the lookup fails as rate limited instead.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    static ref LIMITERS: Mutex<HashMap<&'static str, Limiter>> = Mutex::new(HashMap::new());
+}
+
+/// A token bucket of the requests a provider may be sent.
+struct Limiter {
+    tokens: f64,
+    refilled_at: Instant,
+    /// Set from the `Retry-After` of a provider that answered with 429.
+    blocked_until: Option<Instant>,
+}
+
+impl Limiter {
+    fn new(burst: u64, now: Instant) -> Self {
+        Self {
+            tokens: burst as f64,
+            refilled_at: now,
+            blocked_until: None,
+        }
+    }
+
+    /// Takes a token from a bucket of `burst` tokens refilled by `per_minute` tokens a minute and
+    /// tells how long to wait until it is there. A token more than `max_wait` away is not taken.
+    fn take(
+        &mut self,
+        per_minute: u64,
+        burst: u64,
+        now: Instant,
+        max_wait: Duration,
+    ) -> Result<Duration, Duration> {
+        let per_sec = per_minute as f64 / 60.0;
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * per_sec).min(burst as f64);
+        self.refilled_at = now;
+        let wait = Duration::from_secs_f64((1.0 - self.tokens).max(0.0) / per_sec);
+        if wait > max_wait {
+            return Err(wait);
+        }
+        // the bucket goes below zero, so that the requests waiting for it are queued.
+        self.tokens -= 1.0;
+        Ok(wait)
+    }
+}
+
+fn ceil_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil() as u64
+}
+
+/// The rate per minute and burst of `provider` when the settings have none.
+fn default_rate(provider: &str) -> (u64, u64) {
+    PROVIDER_DEFAULTS
+        .iter()
+        .find(|(name, _, _)| *name == provider)
+        .map_or(
+            (DEFAULT_RATE_PER_MINUTE, DEFAULT_BURST),
+            |(_, per_minute, burst)| (*per_minute, *burst),
+        )
+}
+
+/// Waits for the rate limit of `provider` to allow a request, for `max_wait` at most.
+async fn acquire(provider: &'static str, max_wait: Duration) -> Result<(), ProviderError> {
+    let (default_per_minute, default_burst) = default_rate(provider);
+    let per_minute =
+        setting_u64_entry("onlineRatePerMinute", provider).unwrap_or(default_per_minute);
+    let burst = setting_u64_entry("onlineRateBurst", provider)
+        .unwrap_or(default_burst)
+        .max(1);
+    let now = Instant::now();
+    let wait = {
+        let mut limiters = LIMITERS.lock().unwrap();
+        let limiter = limiters
+            .entry(provider)
+            .or_insert_with(|| Limiter::new(burst, now));
+        match limiter.blocked_until {
+            Some(until) if until > now + max_wait => Err(until - now),
+            Some(until) if until > now => Ok(until - now),
+            _ if per_minute == 0 => Ok(Duration::ZERO),
+            _ => limiter.take(per_minute, burst, now, max_wait),
+        }
+    };
+    let wait = wait.map_err(|wait| ProviderError::RateLimited {
+        retry_after_secs: Some(ceil_secs(wait)),
+    })?;
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+    Ok(())
+}
+
+/// Holds back every request to `provider` for `wait`.
+fn block(provider: &'static str, wait: Duration) {
+    let now = Instant::now();
+    LIMITERS
+        .lock()
+        .unwrap()
+        .entry(provider)
+        .or_insert_with(|| Limiter::new(default_rate(provider).1, now))
+        .blocked_until = Some(now + wait);
+}
+
+/// The wait asked for by `Retry-After`, given either in seconds or as an HTTP date.
+fn retry_after(res: &Response, now: SystemTime) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    parse_retry_after(value, now)
+}
+
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    match value.parse() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        // a date already past asks for no wait at all.
+        Err(_) => Some(
+            httpdate::parse_http_date(value)
+                .ok()?
+                .duration_since(now)
+                .unwrap_or(Duration::ZERO),
+        ),
+    }
+}
+
+/// Between half and all of the doubled base delay, the nanoseconds of the clock are random enough
+/// to keep concurrent lookups from retrying in step.
+fn backoff(attempt: u32) -> Duration {
+    let max = BASE_BACKOFF * 2u32.pow(attempt);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    max / 2 + (max / 2).mul_f64(nanos as f64 / 1e9)
+}
+
+/// Sends the request `build` makes once the rate limit of `provider` allows it, see `acquire`. Answers of 429
+/// and server errors are retried after their `Retry-After`, or with an exponential backoff, each retry
+/// taking its own token. A provider still answering 429, or asking for a wait the time set for it by
+/// `provider_timeout` leaves no room for, is reported as rate limited.
+pub async fn send<F>(provider: &'static str, build: F) -> Result<Response, ProviderError>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let deadline = provider_timeout(provider).map(|limit| Instant::now() + limit);
+    let time_left = || deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+    let mut attempt = 0;
+    loop {
+        let max_wait = time_left().map_or(MAX_ACQUIRE_WAIT, |left| left.min(MAX_ACQUIRE_WAIT));
+        acquire(provider, max_wait).await?;
+        let res = build().send().await?;
+        let status = res.status();
+        if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+            return Ok(res);
+        }
+        let retry_after = retry_after(&res, SystemTime::now());
+        let wait = retry_after.unwrap_or_else(|| backoff(attempt));
+        if attempt == MAX_RETRIES
+            || retry_after.map_or(false, |wait| wait > MAX_RETRY_WAIT)
+            || time_left().map_or(false, |left| wait >= left)
+        {
+            if status != StatusCode::TOO_MANY_REQUESTS {
+                return Err(ProviderError::Http {
+                    status: status.as_u16(),
+                });
+            }
+            if let Some(wait) = retry_after {
+                block(provider, wait);
+            }
+            return Err(ProviderError::RateLimited {
+                retry_after_secs: retry_after.map(ceil_secs),
+            });
+        }
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff, default_rate, parse_retry_after, Limiter, BASE_BACKOFF};
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+
+    #[test]
+    fn tokens_refill_at_the_set_rate() {
+        let start = Instant::now();
+        let no_wait = Duration::ZERO;
+        let mut limiter = Limiter::new(2, start);
+        assert_eq!(limiter.take(60, 2, start, no_wait), Ok(no_wait));
+        assert_eq!(limiter.take(60, 2, start, no_wait), Ok(no_wait));
+        let wait = limiter.take(60, 2, start, no_wait).unwrap_err();
+        assert!(wait > Duration::from_millis(999) && wait <= Duration::from_secs(1));
+        assert_eq!(
+            limiter.take(60, 2, start + Duration::from_secs(1), no_wait),
+            Ok(no_wait)
+        );
+        // a long pause fills the bucket up to the burst only.
+        let later = start + Duration::from_secs(60);
+        assert_eq!(limiter.take(60, 2, later, no_wait), Ok(no_wait));
+        assert_eq!(limiter.take(60, 2, later, no_wait), Ok(no_wait));
+        // waiting requests queue up behind each other.
+        let max_wait = Duration::from_secs(2);
+        let first = limiter.take(60, 2, later, max_wait).unwrap();
+        let second = limiter.take(60, 2, later, max_wait).unwrap();
+        assert!(first <= Duration::from_secs(1) && second > first);
+        assert!(limiter.take(60, 2, later, max_wait).is_err());
+
+        assert!(default_rate("mymemory").0 < default_rate("deepl").0);
+        assert!(default_rate("google").0 < default_rate("cambridge").0);
+
+        for attempt in 0..3 {
+            let max = BASE_BACKOFF * 2u32.pow(attempt);
+            assert!((max / 2..=max).contains(&backoff(attempt)));
+        }
+    }
+
+    #[test]
+    fn retry_after_is_read_as_seconds_or_date() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        let now = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:50:07 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
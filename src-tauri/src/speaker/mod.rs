@@ -1,15 +1,8 @@
 pub mod languages;
 
 use self::languages::Languages;
-use std::{io::Cursor, time::Duration};
-
-lazy_static! {
-    static ref CLIENT: reqwest::Client = reqwest::Client::builder()
-        .connect_timeout(Duration::from_secs(5))
-        .use_rustls_tls()
-        .build()
-        .unwrap();
-}
+use crate::http::client;
+use std::io::Cursor;
 
 #[derive(Debug)]
 pub struct GTTSClient {
@@ -32,7 +25,7 @@ impl GTTSClient {
         let len = text.len();
         let language = Languages::as_code(&self.language);
         let url = format!("https://translate.google.{}/translate_tts?ie=UTF-8&q={}&tl={}&total=1&idx=0&textlen={}&tl={}&client=tw-ob", self.tld, text, language, len, language);
-        let rep = CLIENT
+        let rep = client()
             .get(&url)
             .send()
             .await
@@ -59,7 +52,8 @@ impl GTTSClient {
         }
         let s = s.unwrap();
         s.set_volume(self.volume);
-        Ok(s.sleep_until_end())
+        s.sleep_until_end();
+        Ok(())
     }
 
     /// Speak the input according to the volume and language
@@ -0,0 +1,368 @@
+use crate::helper::{find_absolute_path, unix_time, JsonStore, CACHE_PATH_WITH_IDENTIFIER};
+use ijson::IValue;
+use serde::{Deserialize, Serialize};
+
+pub static VOCABULARY_FILENAME: &str = "vocabulary";
+const DAY_SECS: u64 = 24 * 60 * 60;
+const INITIAL_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+/// Grades below this one are failed reviews, the card starts over.
+const PASSING_GRADE: u8 = 3;
+const MAX_GRADE: u8 = 5;
+
+lazy_static! {
+    pub static ref VOCABULARY: Vocabulary = Vocabulary::new(find_absolute_path(
+        &CACHE_PATH_WITH_IDENTIFIER,
+        VOCABULARY_FILENAME
+    ));
+}
+
+/// The SM-2 state of a card.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Review {
+    /// Passed reviews in a row.
+    pub repetitions: u32,
+    /// Days between the last review and the next one.
+    pub interval: u32,
+    pub ease: f64,
+    /// Seconds since the Unix epoch, new cards are due at once.
+    pub due: u64,
+}
+
+impl Review {
+    fn new(now: u64) -> Self {
+        Self {
+            repetitions: 0,
+            interval: 0,
+            ease: INITIAL_EASE,
+            due: now,
+        }
+    }
+
+    /// The state after answering with `grade`, from 0 (blackout) to 5 (perfect recall).
+    pub fn graded(&self, grade: u8, now: u64) -> Self {
+        let grade = grade.min(MAX_GRADE);
+        let (repetitions, interval) = match (grade >= PASSING_GRADE, self.repetitions) {
+            (false, _) => (0, 1),
+            (true, 0) => (1, 1),
+            (true, 1) => (2, 6),
+            (true, n) => (n + 1, (self.interval as f64 * self.ease).round() as u32),
+        };
+        let miss = (MAX_GRADE - grade) as f64;
+        Self {
+            repetitions,
+            interval,
+            ease: (self.ease + 0.1 - miss * (0.08 + miss * 0.02)).max(MIN_EASE),
+            due: now + interval as u64 * DAY_SECS,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Card {
+    pub id: u64,
+    pub word: String,
+    /// The dictionary or the language the word was looked up in.
+    pub lang: String,
+    /// The lookup result the word was saved with.
+    pub entry: IValue,
+    pub notes: String,
+    pub tags: Vec<String>,
+    /// Seconds since the Unix epoch.
+    pub added: u64,
+    pub review: Review,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VocabList {
+    pub name: String,
+    pub cards: Vec<Card>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+struct Lists {
+    lists: Vec<VocabList>,
+    next_id: u64,
+}
+
+impl Lists {
+    fn list_mut(&mut self, name: &str) -> Result<&mut VocabList, String> {
+        self.lists
+            .iter_mut()
+            .find(|list| list.name == name)
+            .ok_or(format!("vocabulary list {name} not found"))
+    }
+
+    fn card_mut(&mut self, list: &str, id: u64) -> Result<&mut Card, String> {
+        self.list_mut(list)?
+            .cards
+            .iter_mut()
+            .find(|card| card.id == id)
+            .ok_or_else(|| "card not found".to_owned())
+    }
+}
+
+#[derive(Serialize)]
+pub struct VocabListSummary {
+    pub name: String,
+    pub cards: usize,
+    pub due: usize,
+}
+
+#[derive(Serialize)]
+pub struct DueCard {
+    pub list: String,
+    pub card: Card,
+}
+
+/// Named lists of saved words, kept in `{path}.json`.
+pub struct Vocabulary {
+    lists: JsonStore<Lists>,
+}
+
+impl Vocabulary {
+    pub fn new(path: String) -> Self {
+        Self {
+            lists: JsonStore::new(path),
+        }
+    }
+
+    pub fn lists(&self) -> Result<Vec<VocabListSummary>, String> {
+        let now = unix_time();
+        self.lists.update(|lists| {
+            let summaries = lists
+                .lists
+                .iter()
+                .map(|list| VocabListSummary {
+                    name: list.name.clone(),
+                    cards: list.cards.len(),
+                    due: list.cards.iter().filter(|c| c.review.due <= now).count(),
+                })
+                .collect();
+            (summaries, false)
+        })
+    }
+
+    pub fn create_list(&self, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("list name is empty".to_owned());
+        }
+        self.lists.update(|lists| {
+            if lists.lists.iter().any(|list| list.name == name) {
+                return (Err(format!("vocabulary list {name} already exists")), false);
+            }
+            lists.lists.push(VocabList {
+                name: name.to_owned(),
+                cards: Vec::new(),
+            });
+            (Ok(()), true)
+        })?
+    }
+
+    pub fn delete_list(&self, name: &str) -> Result<(), String> {
+        self.lists.update(|lists| {
+            let len = lists.lists.len();
+            lists.lists.retain(|list| list.name != name);
+            (Ok(()), lists.lists.len() != len)
+        })?
+    }
+
+    pub fn cards(&self, list: &str) -> Result<Vec<Card>, String> {
+        self.lists
+            .update(|lists| (lists.list_mut(list).map(|list| list.cards.clone()), false))?
+    }
+
+    /// Saves `word` into `list`. A word already in the list gets the new entry, notes and tags
+    /// and keeps its review schedule.
+    pub fn save(
+        &self,
+        list: &str,
+        word: &str,
+        lang: &str,
+        entry: IValue,
+        notes: &str,
+        tags: Vec<String>,
+    ) -> Result<Card, String> {
+        let word = word.trim();
+        if word.is_empty() {
+            return Err("word is empty".to_owned());
+        }
+        self.lists.update(|lists| {
+            let id = lists.next_id;
+            let list = match lists.list_mut(list) {
+                Ok(list) => list,
+                Err(e) => return (Err(e), false),
+            };
+            let card = match list
+                .cards
+                .iter_mut()
+                .find(|card| card.word == word && card.lang == lang)
+            {
+                Some(card) => {
+                    card.entry = entry;
+                    card.notes = notes.to_owned();
+                    card.tags = tags;
+                    card.clone()
+                }
+                None => {
+                    let now = unix_time();
+                    let card = Card {
+                        id,
+                        word: word.to_owned(),
+                        lang: lang.to_owned(),
+                        entry,
+                        notes: notes.to_owned(),
+                        tags,
+                        added: now,
+                        review: Review::new(now),
+                    };
+                    list.cards.push(card.clone());
+                    lists.next_id += 1;
+                    card
+                }
+            };
+            (Ok(card), true)
+        })?
+    }
+
+    pub fn annotate(
+        &self,
+        list: &str,
+        id: u64,
+        notes: &str,
+        tags: Vec<String>,
+    ) -> Result<(), String> {
+        self.lists.update(|lists| match lists.card_mut(list, id) {
+            Ok(card) => {
+                card.notes = notes.to_owned();
+                card.tags = tags;
+                (Ok(()), true)
+            }
+            Err(e) => (Err(e), false),
+        })?
+    }
+
+    pub fn remove(&self, list: &str, id: u64) -> Result<(), String> {
+        self.lists.update(|lists| match lists.list_mut(list) {
+            Ok(list) => {
+                let len = list.cards.len();
+                list.cards.retain(|card| card.id != id);
+                (Ok(()), list.cards.len() != len)
+            }
+            Err(e) => (Err(e), false),
+        })?
+    }
+
+    /// Up to `limit` cards due for review, of `list` or of every list, the most overdue first.
+    pub fn due(&self, list: Option<&str>, limit: usize) -> Result<Vec<DueCard>, String> {
+        let now = unix_time();
+        self.lists.update(|lists| {
+            let mut due = lists
+                .lists
+                .iter()
+                .filter(|l| list.map_or(true, |list| l.name == list))
+                .flat_map(|l| l.cards.iter().map(move |card| (l, card)))
+                .filter(|(_, card)| card.review.due <= now)
+                .collect::<Vec<_>>();
+            due.sort_by_key(|(_, card)| card.review.due);
+            let due = due
+                .into_iter()
+                .take(limit)
+                .map(|(l, card)| DueCard {
+                    list: l.name.clone(),
+                    card: card.clone(),
+                })
+                .collect();
+            (due, false)
+        })
+    }
+
+    /// Schedules the next review of a card answered with `grade`.
+    pub fn grade(&self, list: &str, id: u64, grade: u8) -> Result<Review, String> {
+        if grade > MAX_GRADE {
+            return Err(format!("grade must be between 0 and {MAX_GRADE}"));
+        }
+        let now = unix_time();
+        self.lists.update(|lists| match lists.card_mut(list, id) {
+            Ok(card) => {
+                card.review = card.review.graded(grade, now);
+                (Ok(card.review.clone()), true)
+            }
+            Err(e) => (Err(e), false),
+        })?
+    }
+
+    /// The interval in days each grade, from 0 to `MAX_GRADE`, would give a card.
+    pub fn next_intervals(&self, list: &str, id: u64) -> Result<Vec<u32>, String> {
+        let now = unix_time();
+        self.lists.update(|lists| {
+            let intervals = lists.card_mut(list, id).map(|card| {
+                (0..=MAX_GRADE)
+                    .map(|grade| card.review.graded(grade, now).interval)
+                    .collect()
+            });
+            (intervals, false)
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Review, Vocabulary, DAY_SECS};
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn reviews_follow_sm2() {
+        let review = Review::new(0);
+        let first = review.graded(5, 0);
+        assert_eq!((first.repetitions, first.interval), (1, 1));
+        let second = first.graded(4, 0);
+        assert_eq!((second.repetitions, second.interval), (2, 6));
+        let third = second.graded(4, 0);
+        assert_eq!(third.interval, (6.0 * second.ease).round() as u32);
+        assert_eq!(third.due, third.interval as u64 * DAY_SECS);
+
+        let failed = third.graded(1, 0);
+        assert_eq!((failed.repetitions, failed.interval), (0, 1));
+        assert!(failed.ease < third.ease);
+        let mut hard = review;
+        for _ in 0..10 {
+            hard = hard.graded(3, 0);
+        }
+        assert_eq!(hard.ease, 1.3);
+    }
+
+    #[test]
+    fn cards_are_saved_and_reviewed() {
+        let tmp = TempDir::new("vocabulary");
+        let path = tmp.path().join("vocabulary");
+        let path = path.to_str().unwrap().to_owned();
+        let vocabulary = Vocabulary::new(path.clone());
+        vocabulary.create_list("German").unwrap();
+        assert!(vocabulary.create_list("German").is_err());
+        let entry = ijson::ijson!({"pos": "noun"});
+        let card = vocabulary
+            .save("German", "Haus", "de", entry.clone(), "", vec![])
+            .unwrap();
+        let same = vocabulary
+            .save("German", "Haus", "de", entry, "das Haus", vec!["A1".into()])
+            .unwrap();
+        assert_eq!(card.id, same.id);
+
+        let vocabulary = Vocabulary::new(path.clone());
+        assert_eq!(vocabulary.due(None, 10).unwrap().len(), 1);
+        assert_eq!(
+            vocabulary.next_intervals("German", card.id).unwrap(),
+            [1, 1, 1, 1, 1, 1]
+        );
+        vocabulary.grade("German", card.id, 4).unwrap();
+        assert!(vocabulary.due(Some("German"), 10).unwrap().is_empty());
+        let cards = vocabulary.cards("German").unwrap();
+        assert_eq!(cards[0].notes, "das Haus");
+        assert_eq!(vocabulary.lists().unwrap()[0].due, 0);
+        vocabulary.remove("German", card.id).unwrap();
+        vocabulary.delete_list("German").unwrap();
+        assert!(vocabulary.lists().unwrap().is_empty());
+    }
+}
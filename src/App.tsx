@@ -9,10 +9,10 @@ import { OfflineTab } from './components/language-options/offline-mode/OfflineTa
 import { OnlineTab } from './components/language-options/OnlineTab';
 import { Translation, TranslationCompOutput } from './components/Translation';
 import { CountriesAbbrs, SavedConfig } from './models/countries';
-import { OfflineDictAbbrs, OfflineDictsList } from './models/offline-mode';
+import { DictBuildPhase, OfflineDictAbbrs, OfflineDictsList } from './models/offline-mode';
 import { Theme } from './models/theme';
 
-type DownloadStatus = { name: OfflineDictAbbrs; percentage: number };
+type DownloadStatus = { name: OfflineDictAbbrs; phase: DictBuildPhase; percentage: number };
 
 function App() {
   const [activeTab, setActiveTab] = useState<'online' | 'offline'>('online');
@@ -43,6 +43,9 @@ function App() {
   const shouldTranslateClipboardRef = useRef(false);
   const _shouldTranslateSelectedTextRef = useRef(false);
   const tranlationCompRef = createRef<TranslationCompOutput>();
+  // settings only the backend reads are written back as they were loaded.
+  const savedConfigRef = useRef<Partial<SavedConfig>>({});
+  const onlineProvidersRef = useRef<string[]>();
 
   let selectedTheme: Theme = "default";
 
@@ -57,6 +60,7 @@ function App() {
     const dd = downloadedDicts || downloadedDictsRef.current;
 
     const config: SavedConfig = {
+      ...savedConfigRef.current,
       theme: selectedTheme,
       activeTab: activeTabRef.current,
       from: fromRef.current,
@@ -80,7 +84,10 @@ function App() {
     }
 
     once<SavedConfig>('get_saved_config',
-      ({ payload: { theme, activeTab, from, to, selectedOfflineDict, downloadedDicts, shouldTranslateClipboard: tc, shouldTranslateSelectedText: ts } }) => {
+      ({ payload }) => {
+        savedConfigRef.current = payload;
+        onlineProvidersRef.current = payload.onlineProviders;
+        const { theme, activeTab, from, to, selectedOfflineDict, downloadedDicts, shouldTranslateClipboard: tc, shouldTranslateSelectedText: ts } = payload;
         activeTab && setActiveTab(activeTab);
         from && setFrom(from);
         to && setTo(to);
@@ -95,6 +102,7 @@ function App() {
     const themeListener = listen<Theme>('theme_changed', ({ payload }) => changeTheme(payload));
     const downloadingListener = listen<DownloadStatus>('downloading', ({ payload }) => {
       offlineDictsList[payload.name].percentage = payload.percentage;
+      offlineDictsList[payload.name].phase = payload.phase;
       setOfflineDictsList({ ...offlineDictsList });
     });
 
@@ -194,6 +202,7 @@ function App() {
         offlineDictsList={offlineDictsList}
         shouldTranslateClipboardRef={shouldTranslateClipboardRef}
         _shouldTranslateSelectedTextRef={_shouldTranslateSelectedTextRef}
+        onlineProvidersRef={onlineProvidersRef}
       />
 
       <span className={styles.version}>v.{version}</span>
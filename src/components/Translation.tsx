@@ -4,17 +4,20 @@ import { listen } from '@tauri-apps/api/event';
 import { appWindow, PhysicalPosition } from '@tauri-apps/api/window';
 import React, { BaseSyntheticEvent, MutableRefObject, useEffect, useImperativeHandle, useMemo, useRef, useState } from 'react';
 import { CountriesAbbrs } from '../models/countries';
-import { INIT_DICT as INIT_DICT_MSG, OfflineDictAbbrs, OfflineDictsList, OfflineTranslation } from '../models/offline-mode';
-import { OnlineTranslation } from '../models/online.mode';
+import { LookupSource } from '../models/history';
+import { INIT_DICT as INIT_DICT_MSG, OfflineDictAbbrs, OfflineDictsList, OfflineLookup, OfflineTranslation } from '../models/offline-mode';
+import { OnlineDoneEvent, OnlineResultEvent, OnlineTranslation } from '../models/online.mode';
 import styles from './Translation.module.scss';
 
 export type TranslationCompOutput = {
     translate: () => void,
     langSwapped: () => void,
-    translationTextareaRef: MutableRefObject<string | OnlineTranslation | OfflineTranslation>,
+    translationTextareaRef: MutableRefObject<string | OnlineTranslation | OfflineLookup>,
 }
 
 const SEARCHING_TRANS = "searching...";
+/** order of the online results when `onlineProviders` is not set */
+const DEFAULT_PROVIDERS_ORDER = ["google", "deepl", "cambridge", "mymemory", "sentencedict"];
 
 export const Translation = React.forwardRef(({
     activeTabRef,
@@ -23,6 +26,7 @@ export const Translation = React.forwardRef(({
     selectedOfflineDictRef,
     shouldTranslateClipboardRef,
     _shouldTranslateSelectedTextRef,
+    onlineProvidersRef,
     offlineDictsList,
     emitNewConfig,
 }: {
@@ -32,6 +36,7 @@ export const Translation = React.forwardRef(({
     selectedOfflineDictRef: MutableRefObject<OfflineDictAbbrs | undefined>,
     shouldTranslateClipboardRef: MutableRefObject<boolean>,
     _shouldTranslateSelectedTextRef: MutableRefObject<boolean>,
+    onlineProvidersRef: MutableRefObject<string[] | undefined>,
     offlineDictsList: OfflineDictsList,
     emitNewConfig(selectedOfflineDict?: OfflineDictAbbrs | null, downloadedDicts?: OfflineDictAbbrs[]): Promise<void>,
 }, ref: ((instance: any) => void) | MutableRefObject<TranslationCompOutput | null> | null) => {
@@ -40,7 +45,12 @@ export const Translation = React.forwardRef(({
     const isSpeaking = useRef(false);
     const timeout = useRef<number>();
     const fieldsetRef = useRef<HTMLDivElement>(null);
-    const translationTextareaRef = useRef<string | OnlineTranslation | OfflineTranslation>('');
+    const translationTextareaRef = useRef<string | OnlineTranslation | OfflineLookup>('');
+    // the streamed online lookup on display, and the results gathered for it and any newer one
+    // whose results arrived before its id did.
+    const onlineRequestRef = useRef<number>();
+    const onlineResultsRef = useRef<Record<number, OnlineTranslation>>({});
+    const [, setOnlineResultCount] = useState(0);
 
     let clipboardBuffer: string;
 
@@ -53,18 +63,20 @@ export const Translation = React.forwardRef(({
         setLoading(true);
     }
 
-    const invokeBackend = async (word: string) => {
+    const invokeBackend = async (word: string, source?: LookupSource) => {
         try {
             if (activeTabRef.current === 'online') {
                 const from = fromRef.current, to = toRef.current;
-                const d = await invoke<OnlineTranslation>('online_translate', { from, to, word });
-                if (word === inputRef.current?.value && from === fromRef.current && to === toRef.current) {
-                    translationTextareaRef.current = d;
-                    setLoading(false);
+                const requestId = await invoke<number>('online_translate_stream', { from, to, word, source });
+                if (onlineRequestRef.current !== undefined && requestId < onlineRequestRef.current) return;
+                onlineRequestRef.current = requestId;
+                for (const id in onlineResultsRef.current) {
+                    if (+id < requestId) delete onlineResultsRef.current[id];
                 }
+                showOnlineResults(requestId);
             } else {
                 if (!selectedOfflineDictRef.current) return;
-                translationTextareaRef.current = await invoke<OfflineTranslation>('offline_translate', { word, lang: selectedOfflineDictRef.current });
+                translationTextareaRef.current = await invoke<OfflineLookup>('offline_translate', { word, lang: selectedOfflineDictRef.current, source });
                 setLoading(false);
             }
         } catch (er: unknown) {
@@ -73,10 +85,19 @@ export const Translation = React.forwardRef(({
         }
     }
 
-    const search = async (word: string | undefined) => {
+    const showOnlineResults = (requestId: number) => {
+        const results = onlineResultsRef.current[requestId];
+        if (!results || requestId !== onlineRequestRef.current || activeTabRef.current !== 'online') return;
+        translationTextareaRef.current = { ...results };
+        setLoading(false);
+        setOnlineResultCount(count => count + 1);
+    }
+
+    // `source` is left out for lookups made while typing, the backend keeps them out of the history.
+    const search = async (word: string | undefined, source?: LookupSource) => {
         if (!word?.trim()) return clearInput(translationTextareaRef.current === SEARCHING_TRANS);
         setTransRefLoadingState();
-        await invokeBackend(word);
+        await invokeBackend(word, source);
         setTimeout(() => {
             fieldsetRef.current?.scrollTo({ top: 0, behavior: 'smooth' });
         }, 100);
@@ -102,11 +123,11 @@ export const Translation = React.forwardRef(({
     }
 
     const renderOnlineTranslations = () => {
-        if (typeof translationTextareaRef.current === 'string' || !('google' in translationTextareaRef.current)) return;
+        if (typeof translationTextareaRef.current === 'string' || 'entries' in translationTextareaRef.current) return;
 
-        const { google, cambridge, sentencedict, mymemory } = translationTextareaRef.current;
+        const { google, deepl, cambridge, sentencedict, mymemory, errors } = translationTextareaRef.current;
 
-        const mymemoryTrans = <><h3 style={{ color: "mediumvioletred" }}>AI:</h3>{mymemory.map(({ accuracy, segment: word, translation }) =>
+        const mymemoryTrans = <><h3 style={{ color: "mediumvioletred" }}>AI:</h3>{mymemory?.map(({ accuracy, segment: word, translation }) =>
             <div className={styles.definitions} style={{ marginBlock: ".5rem", backgroundColor: "rgb(var(--primary), .2)" }} key={word + translation}>
                 <div><span style={{ color: "rgb(var(--sky))" }}>Word:</span> {word}</div>
                 <div><span style={{ color: "rgb(var(--sky))" }}>Translation:</span> {translation}</div>
@@ -158,10 +179,8 @@ export const Translation = React.forwardRef(({
 
         const sentencedictTrans = sentencedictParser();
 
-
-        return (
-            <div className={styles.onlineMode}>
-                <h3>Google:</h3>
+        const sections: Record<string, false | JSX.Element> = {
+            google: google !== undefined && <><h3>Google:</h3>
                 <div className={styles.google}
                     style={{
                         direction: activeTabRef.current === 'online' && (toRef.current === 'fa' || toRef.current === 'ar') ? 'rtl' : 'ltr',
@@ -169,31 +188,95 @@ export const Translation = React.forwardRef(({
                 >
                     {google}
                 </div>
+            </>,
 
-                {!!cambridgeTrans && <><h3>Cambridge:</h3>
-                    <div className={styles.definitions} dangerouslySetInnerHTML={{ __html: cambridgeTrans }}></div>
-                </>}
+            deepl: !!deepl && <><h3>DeepL:</h3>
+                <div className={styles.google}
+                    style={{
+                        direction: activeTabRef.current === 'online' && (toRef.current === 'fa' || toRef.current === 'ar') ? 'rtl' : 'ltr',
+                    }}
+                >
+                    {deepl}
+                </div>
+            </>,
 
-                {!!mymemory.length && <>{mymemoryTrans}</>}
+            cambridge: !!cambridgeTrans && <><h3>Cambridge:</h3>
+                <div className={styles.definitions} dangerouslySetInnerHTML={{ __html: cambridgeTrans }}></div>
+            </>,
 
-                {!!sentencedictTrans && <><hr /><h4 style={{ color: "rgb(var(--warning), .8)", fontStyle: "italic", fontSize: ".9rem" }}>Gathered from Websites:</h4>
-                    <div className={styles.definitions} dangerouslySetInnerHTML={{ __html: sentencedictTrans.defStr }}></div>
-                    <h4>Examples:</h4>
-                    <div className={styles.examples} dangerouslySetInnerHTML={{ __html: sentencedictTrans.examples }}></div>
-                </>}
+            mymemory: !!mymemory?.length && <>{mymemoryTrans}</>,
+
+            sentencedict: !!sentencedictTrans && <><hr /><h4 style={{ color: "rgb(var(--warning), .8)", fontStyle: "italic", fontSize: ".9rem" }}>Gathered from Websites:</h4>
+                <div className={styles.definitions} dangerouslySetInnerHTML={{ __html: sentencedictTrans.defStr }}></div>
+                <h4>Examples:</h4>
+                <div className={styles.examples} dangerouslySetInnerHTML={{ __html: sentencedictTrans.examples }}></div>
+            </>,
+        };
+
+        return (
+            <div className={styles.onlineMode}>
+                {[...new Set(onlineProvidersRef.current ?? DEFAULT_PROVIDERS_ORDER)].map(provider =>
+                    <React.Fragment key={provider}>{sections[provider]}</React.Fragment>
+                )}
+
+                {!!errors?.length && <div className={styles.providerErrors}>
+                    {errors.map((error) =>
+                        <small key={error.provider}>
+                            {error.provider} failed: {
+                                error.kind === 'http' ? `HTTP ${error.status}` :
+                                    error.kind === 'timeout' ? `no answer in ${error.seconds}s` :
+                                        error.kind === 'rateLimited' ? `asked too often${error.retryAfterSecs ? `, retry in ${error.retryAfterSecs}s` : ''}` :
+                                            error.message
+                            }
+                        </small>
+                    )}
+                </div>}
             </div>
         )
     }
 
     const renderOfflineTranslations = () => {
-        if (typeof translationTextareaRef.current === 'string' || !('pos' in translationTextareaRef.current)) return;
-        return (
+        if (typeof translationTextareaRef.current === 'string' || !('entries' in translationTextareaRef.current)) return;
+        const { word, entries, lemmas, did_you_mean } = translationTextareaRef.current;
+        if (!entries.length && !lemmas.length) return (
             <div className={styles.offlineMode}>
+                <h3>Did you mean:</h3>
+                <div className={styles.senses}>
+                    {did_you_mean.map(({ word }) =>
+                        <p key={word} style={{ cursor: 'pointer' }} onClick={() => {
+                            if (inputRef.current) inputRef.current.value = word;
+                            search(word, activeTabRef.current);
+                        }}>{word}</p>
+                    )}
+                </div>
+            </div>
+        );
+        return <>
+            {entries.map(renderOfflineEntry)}
+            {lemmas.map(({ lemma, entries }) => <div key={lemma}>
+                <h3>"{word}" is a form of "{lemma}":</h3>
+                {entries.map(renderOfflineEntry)}
+            </div>)}
+        </>
+    }
+
+    const renderOfflineEntry = (entry: OfflineTranslation, i: number) => {
+        if (entry.definition) return (
+            <div key={i} className={styles.offlineMode}>
+                <h3>Definition:</h3>
+                {entry.definition.format === 'html'
+                    // an empty sandbox keeps scripts and links of the imported markup from running.
+                    ? <iframe className={styles.importedDefinition} sandbox="" srcDoc={entry.definition.text} />
+                    : <div className={styles.importedDefinition}>{entry.definition.text}</div>}
+            </div>
+        );
+        return (
+            <div key={i} className={styles.offlineMode}>
                 <h3>Position:</h3>
-                <div className={styles.pos}>{translationTextareaRef.current.pos}</div>
+                <div className={styles.pos}>{entry.pos}</div>
                 <h3>Senses:</h3>
                 <div className={styles.senses}>
-                    {translationTextareaRef.current.senses.map(s => {
+                    {entry.senses.map(s => {
                         return <div key={s.glosses.join('')}>
                             {!!s.categories?.length && <p key={s.categories[0].name}><span>Categories:</span> {s.categories.map(c => c.name).join(", ")}</p>}
                             <p><span>Glosses:</span> {s.glosses.join(' ')}</p>
@@ -216,14 +299,14 @@ export const Translation = React.forwardRef(({
                     })}
                 </div>
 
-                {translationTextareaRef.current.etymology_text && <div>
+                {entry.etymology_text && <div>
                     <h3 key="ety text">Etymology text:</h3>
-                    <div className={styles.definitions}>{translationTextareaRef.current.etymology_text}</div>
+                    <div className={styles.definitions}>{entry.etymology_text}</div>
                 </div>}
-                {translationTextareaRef.current.etymology_templates && !!translationTextareaRef.current.etymology_templates.length && <div>
+                {entry.etymology_templates && !!entry.etymology_templates.length && <div>
                     <h3 key="ety temp">Etymology templates:</h3>
                     <div className={styles.definitions}>
-                        {translationTextareaRef.current.etymology_templates.filter(et => et.expansion).map(et => et.expansion).join(", ")}
+                        {entry.etymology_templates.filter(et => et.expansion).map(et => et.expansion).join(", ")}
                     </div>
                 </div>}
             </div>
@@ -258,7 +341,7 @@ export const Translation = React.forwardRef(({
             clipboardBuffer = trimmed;
             if (inputRef.current) inputRef.current.value = trimmed;
             setTransRefLoadingState();
-            setTimeout(() => search(trimmed), 100);
+            setTimeout(() => search(trimmed, 'clipboard'), 100);
         }
 
         const displayWindow = async () => {
@@ -302,7 +385,7 @@ export const Translation = React.forwardRef(({
             if (inputRef.current) inputRef.current.value = trimmed;
             consumeClipboard();
             setTransRefLoadingState();
-            setTimeout(() => search(trimmed), 100);
+            setTimeout(() => search(trimmed, activeTabRef.current), 100);
         }
 
         function translationSpeakHandler(e: KeyboardEvent) {
@@ -346,10 +429,24 @@ export const Translation = React.forwardRef(({
             if (inputRef.current) inputRef.current.value = text;
             consumeClipboard();
             setTransRefLoadingState();
-            setTimeout(() => search(text), 100);
+            setTimeout(() => search(text, 'selection'), 100);
             displayWindow();
         });
 
+        const onlineResultListener = listen<OnlineResultEvent>('online_result', ({ payload }) => {
+            const { requestId, provider, output, error } = payload;
+            if (onlineRequestRef.current !== undefined && requestId < onlineRequestRef.current) return;
+            const results = onlineResultsRef.current[requestId] ??= { errors: [] };
+            if (error) results.errors?.push({ provider, ...error });
+            else (results as Record<string, unknown>)[provider] = output;
+            showOnlineResults(requestId);
+        });
+
+        const onlineDoneListener = listen<OnlineDoneEvent>('online_done', ({ payload }) => {
+            onlineResultsRef.current[payload.requestId] ??= { errors: [] };
+            showOnlineResults(payload.requestId);
+        });
+
         const translateClipboardListener = listen<void>('text_copied', async () => {
             const res = await translateClip();
             res ?? displayWindow();
@@ -363,6 +460,8 @@ export const Translation = React.forwardRef(({
             window.removeEventListener('drop', dropTextHandler);
             appFocus.then(d => d());
             translateClipboardListener.then(d => d());
+            onlineResultListener.then(d => d());
+            onlineDoneListener.then(d => d());
             translateSelectedTextListener.then(d => d());
             trayListener.then(d => d());
         }
@@ -408,7 +507,7 @@ export const Translation = React.forwardRef(({
                         left: isLatin ? 'unset' : '2px',
                         right: isLatin ? '2px' : 'unset'
                     }}>
-                    <button className="glow-animation" onClick={() => search(inputRef.current?.value)}></button>
+                    <button className="glow-animation" onClick={() => search(inputRef.current?.value, activeTabRef.current)}></button>
                     <button className="glow-animation" disabled={loading && activeTabRef.current === 'offline'} onClick={() => clearInput(translationTextareaRef.current === SEARCHING_TRANS)}></button>
                 </div>
             </div>
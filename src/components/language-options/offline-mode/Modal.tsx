@@ -9,7 +9,6 @@ import styles from "./Modal.module.scss";
 
 export const NOT_DOWNLOADED = -2;
 const DOWNLOAD_STARTED = -1;
-const WAIT_FOR_PROCESSING = 100;
 const DOWNLOADED = 101;
 
 export const Modal: React.FC<{
@@ -58,10 +57,12 @@ export const Modal: React.FC<{
             setDownloadedDicts(downloadedDicts.slice());
             emitNewConfig(selectedOfflineDict, downloadedDicts.slice());
             offlineDictsList[abbr].percentage = DOWNLOADED;
+            offlineDictsList[abbr].phase = undefined;
           })
           .catch(possibleErrOrCancelation => {
             console.error('error for', abbr, possibleErrOrCancelation)
             offlineDictsList[abbr].percentage = NOT_DOWNLOADED;
+            offlineDictsList[abbr].phase = undefined;
           })
           .finally(() => setOfflineDictsList({ ...offlineDictsList }));
       } else {  //* to cancel
@@ -79,16 +80,13 @@ export const Modal: React.FC<{
             <div className={styles.download}>
               {dict.percentage !== NOT_DOWNLOADED && dict.percentage !== DOWNLOADED &&
                 <span>
-                  {dict.percentage === WAIT_FOR_PROCESSING ? <h6>processing</h6> : dict.percentage === DOWNLOAD_STARTED ? <h6>initializing</h6> : `${dict.percentage}%`}
+                  {dict.percentage === DOWNLOAD_STARTED ? <h6>initializing</h6> : <><h6>{dict.phase}</h6>{`${dict.percentage}%`}</>}
                 </span>
               }
               <button
-                disabled={dict.percentage === WAIT_FOR_PROCESSING}
                 style={{
                   backgroundImage: `url(${dlStatusIcon})`,
                   backgroundSize: '20px',
-                  opacity: dict.percentage === WAIT_FOR_PROCESSING ? '0.5' : '',
-                  cursor: dict.percentage === WAIT_FOR_PROCESSING ? 'default' : '',
                 }}
                 onClick={() => downloadCancelDelete(abbr)}>
               </button>
@@ -146,4 +146,28 @@ export type SavedConfig = {
     height: number;
     shouldTranslateClipboard: boolean;
     shouldTranslateSelectedText: boolean;
+    /** lookups kept in the history, 0 turns it off */
+    historyLimit?: number;
+    /** hours an online result is served from the cache, 0 turns the cache off */
+    onlineCacheTtlHours?: number;
+    onlineCacheMaxMb?: number;
+    /** online providers to ask, in the order their results are listed */
+    onlineProviders?: string[];
+    /** seconds each online provider is given to answer, 0 for no limit */
+    onlineTimeoutSecs?: number;
+    /** per provider overrides of `onlineTimeoutSecs` */
+    onlineProviderTimeoutSecs?: { [provider: string]: number };
+    /** requests each online provider may be sent a minute, 0 for no limit */
+    onlineRatePerMinute?: { [provider: string]: number };
+    /** requests each online provider may be sent at once before the rate limit applies */
+    onlineRateBurst?: { [provider: string]: number };
+    /** enables the DeepL provider, keys of the free API end with `:fx` */
+    deeplApiKey?: string;
+    deeplFormality?: 'default' | 'more' | 'less';
+    /** an http://, https:// or socks5:// proxy all requests go through */
+    httpProxy?: string;
+    httpUserAgent?: string;
+    /** seconds a whole request may take, 0 for no limit; dictionary downloads are never limited */
+    httpTimeoutSecs?: number;
+    httpConnectTimeoutSecs?: number;
 }
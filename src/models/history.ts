@@ -0,0 +1,11 @@
+export type LookupSource = 'offline' | 'online' | 'selection' | 'clipboard';
+export type HistoryEntry = {
+    id: number;
+    word: string;
+    from: string;
+    /** missing for offline lookups */
+    to?: string;
+    source: LookupSource;
+    /** seconds since the Unix epoch */
+    timestamp: number;
+}
@@ -2,7 +2,8 @@ import { offlineDictionaries } from "./countries";
 
 export type OfflineDictNames = keyof typeof offlineDictionaries;
 export type OfflineDictAbbrs = typeof offlineDictionaries[OfflineDictNames];
-export type OfflineDictsList = { [key in OfflineDictAbbrs]: { percentage: number; zipped: string; extracted: string; name: OfflineDictNames, isBootUp: boolean } };
+export type DictBuildPhase = 'downloading' | 'extracting' | 'converting' | 'indexing';
+export type OfflineDictsList = { [key in OfflineDictAbbrs]: { percentage: number; phase?: DictBuildPhase; zipped: string; extracted: string; name: OfflineDictNames, isBootUp: boolean } };
 export type OfflineTranslation = {
     etymology_text?: string;
     etymology_templates?: { expansion: string; }[];
@@ -23,5 +24,13 @@ export type OfflineTranslation = {
     related: { word: string; }[];
     forms?: { form: string; tags: string[]; };
     sounds?: ({ ipa: string; tags?: string[]; } | { homophone: string; })[];
+    /** the untouched definition of an entry imported from a StarDict or dictd dictionary */
+    definition?: { format: 'html' | 'text'; text: string; };
+}
+export type OfflineLookup = {
+    word: string;
+    entries: OfflineTranslation[];
+    lemmas: { lemma: string; entries: OfflineTranslation[]; }[];
+    did_you_mean: { word: string; distance: number; }[];
 }
 export const INIT_DICT = "initializing, wait for a moment...";
\ No newline at end of file
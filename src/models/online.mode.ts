@@ -1,12 +1,38 @@
+/** keyed by provider, a provider is missing when it is disabled or has nothing for the language pair */
 export type OnlineTranslation = {
-  google: string;
-  cambridge: string;
-  sentencedict: string;
-  mymemory: MyMemoryTranslation
+  google?: string;
+  deepl?: string;
+  cambridge?: string;
+  sentencedict?: string;
+  mymemory?: MyMemoryTranslation;
+  /** providers that were asked and failed */
+  errors?: ProviderFailure[];
 }
 
+export type ProviderError =
+  | { kind: 'network'; message: string }
+  | { kind: 'http'; status: number }
+  | { kind: 'parse'; message: string }
+  | { kind: 'timeout'; seconds: number }
+  | { kind: 'rateLimited'; retryAfterSecs?: number };
+
+export type ProviderFailure = ProviderError & { provider: string };
+
 type MyMemoryTranslation = {
   segment: string;
   translation: string;
   accuracy: number;
-}[]
\ No newline at end of file
+}[]
+/** payload of the `online_result` event, one per provider of a streamed lookup */
+export type OnlineResultEvent = {
+  requestId: number;
+  provider: keyof Omit<OnlineTranslation, 'errors'>;
+  output?: OnlineTranslation[keyof Omit<OnlineTranslation, 'errors'>];
+  error?: ProviderError;
+}
+
+/** payload of the `online_done` event, sent after the last `online_result` of a lookup */
+export type OnlineDoneEvent = {
+  requestId: number;
+  found: boolean;
+}
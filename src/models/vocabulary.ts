@@ -0,0 +1,22 @@
+export type Review = {
+    repetitions: number;
+    /** days between the last review and the next one */
+    interval: number;
+    ease: number;
+    /** seconds since the Unix epoch */
+    due: number;
+}
+export type VocabCard<Entry = unknown> = {
+    id: number;
+    word: string;
+    lang: string;
+    entry: Entry;
+    notes: string;
+    tags: string[];
+    added: number;
+    review: Review;
+}
+export type VocabListSummary = { name: string; cards: number; due: number; }
+export type DueCard = { list: string; card: VocabCard; }
+/** 0 (blackout) to 5 (perfect recall), below 3 the card starts over */
+export type ReviewGrade = 0 | 1 | 2 | 3 | 4 | 5;